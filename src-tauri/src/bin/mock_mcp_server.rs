@@ -0,0 +1,674 @@
+//! Scriptable MCP stdio server for development and tests.
+//!
+//! Serves the tools, resources and prompts of a scenario file and can be made to
+//! misbehave on purpose, so every failure path of the process manager can be
+//! reproduced without a real server.
+//!
+//! ```text
+//! mock-mcp-server [--scenario <file.json>] [--framing ndjson|content-length]
+//!                 [--banner <text>] [--stderr-spam <lines>]
+//!                 [--startup-delay-ms <ms>] [--crash-after <requests>]
+//! mock-mcp-server --replay <recording.jsonl> [--framing ...]
+//! ```
+//!
+//! Besides the scripted tools, two are always available:
+//! - `echo` returns its arguments as text.
+//! - `fault` applies the faults given in its `faults` argument, then echoes.
+//!
+//! A fault is one of (tagged by `kind`):
+//! - `{"kind":"delay","ms":500}` waits before answering.
+//! - `{"kind":"stderr","lines":100,"text":"..."}` writes to stderr first.
+//! - `{"kind":"malformed","output":"{not json"}` writes raw output instead of the
+//!   response.
+//! - `{"kind":"error","code":-32000,"message":"..."}` answers with a JSON-RPC error.
+//! - `{"kind":"hang"}` never answers this request; later ones are still served.
+//! - `{"kind":"crash","exitCode":3,"stderr":"..."}` exits without answering.
+//!
+//! Requests are handled one at a time, so a delay holds back later requests too.
+//!
+//! With `--replay`, the server plays back a recording made with `recordTraffic`
+//! instead: each client message is matched to the next recorded one with the same
+//! method, and the server messages recorded after it are sent back, with response
+//! ids mapped to the ones the client used this time. Messages recorded before the
+//! client's first one are sent at startup; recorded timing is not reproduced.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::Duration;
+
+const PROTOCOL_VERSION: &str = "2025-03-26";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Scenario {
+    server_name: Option<String>,
+    /// Non-JSON line printed to stdout at startup, like many real servers do.
+    banner: Option<String>,
+    /// Lines written to stderr at startup.
+    stderr_spam: usize,
+    startup_delay_ms: u64,
+    /// Exit with code 1 after answering this many requests.
+    crash_after: Option<usize>,
+    tools: Vec<MockTool>,
+    resources: Vec<MockResource>,
+    prompts: Vec<MockPrompt>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockTool {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "empty_object_schema")]
+    input_schema: Value,
+    /// `content` of the call result; the arguments are echoed as text if unset.
+    #[serde(default)]
+    content: Option<Value>,
+    #[serde(default)]
+    is_error: bool,
+    #[serde(default)]
+    faults: Vec<Fault>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockResource {
+    uri: String,
+    name: String,
+    #[serde(default = "text_plain")]
+    mime_type: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockPrompt {
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// Text of the single user message `prompts/get` returns.
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+enum Fault {
+    Delay {
+        ms: u64,
+    },
+    Stderr {
+        #[serde(default = "one")]
+        lines: usize,
+        #[serde(default)]
+        text: Option<String>,
+    },
+    Malformed {
+        #[serde(default)]
+        output: Option<String>,
+    },
+    Error {
+        #[serde(default = "server_error_code")]
+        code: i64,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    Hang,
+    Crash {
+        #[serde(default = "one_i32")]
+        exit_code: i32,
+        #[serde(default)]
+        stderr: Option<String>,
+    },
+}
+
+fn empty_object_schema() -> Value {
+    json!({ "type": "object" })
+}
+
+fn text_plain() -> String {
+    "text/plain".to_string()
+}
+
+fn one() -> usize {
+    1
+}
+
+fn one_i32() -> i32 {
+    1
+}
+
+fn server_error_code() -> i64 {
+    -32000
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Framing {
+    Ndjson,
+    ContentLength,
+}
+
+/// What to do with a request once its faults have been applied.
+enum Reply {
+    Result(Value),
+    Error(i64, String),
+    Raw(String),
+    None,
+}
+
+/// Line of a recording, as written by `mcp::recorder`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedMessage {
+    direction: String,
+    message: Value,
+}
+
+struct Replay {
+    /// Recorded messages in order, with whether the client sent them.
+    entries: Vec<(bool, Value)>,
+    cursor: usize,
+    /// Recorded request id -> id the client used for the matching request.
+    ids: Vec<(Value, Value)>,
+}
+
+impl Replay {
+    fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read recording {}: {}", path, e))?;
+        let entries = text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<RecordedMessage>(line).ok())
+            .map(|entry| (entry.direction == "outbound", entry.message))
+            .collect();
+        Ok(Self {
+            entries,
+            cursor: 0,
+            ids: Vec::new(),
+        })
+    }
+
+    /// Server messages up to the next client message in the recording.
+    fn server_messages(&mut self) -> Vec<Value> {
+        let mut messages = Vec::new();
+        while let Some((false, message)) = self.entries.get(self.cursor) {
+            let mut message = message.clone();
+            if message.get("method").is_none() {
+                if let Some((_, live)) = self
+                    .ids
+                    .iter()
+                    .find(|(recorded, _)| Some(recorded) == message.get("id"))
+                {
+                    message["id"] = live.clone();
+                }
+            }
+            messages.push(message);
+            self.cursor += 1;
+        }
+        messages
+    }
+
+    /// Replies to `message`, or `None` if the rest of the recording has nothing
+    /// like it.
+    fn answer(&mut self, message: &Value) -> Option<Vec<Value>> {
+        let key = replay_key(message)?;
+        let offset = self.entries[self.cursor..]
+            .iter()
+            .position(|(outbound, recorded)| {
+                *outbound && replay_key(recorded).as_ref() == Some(&key)
+            })?;
+        let recorded = &self.entries[self.cursor + offset].1;
+        if let (Some(recorded_id), Some(live_id)) = (recorded.get("id"), message.get("id")) {
+            self.ids.push((recorded_id.clone(), live_id.clone()));
+        }
+        self.cursor += offset + 1;
+        Some(self.server_messages())
+    }
+}
+
+// Requests and notifications match by method, responses to server requests by id
+fn replay_key(message: &Value) -> Option<String> {
+    match message.get("method").and_then(Value::as_str) {
+        Some(method) => Some(format!("method:{}", method)),
+        None => message.get("id").map(|id| format!("response:{}", id)),
+    }
+}
+
+struct Server {
+    scenario: Scenario,
+    framing: Framing,
+    stdout: io::Stdout,
+    answered: usize,
+    replay: Option<Replay>,
+}
+
+impl Server {
+    fn write_message(&mut self, message: &str) {
+        let mut out = self.stdout.lock();
+        let written = match self.framing {
+            Framing::Ndjson => writeln!(out, "{}", message),
+            Framing::ContentLength => {
+                write!(out, "Content-Length: {}\r\n\r\n{}", message.len(), message)
+            }
+        };
+        if written.and_then(|_| out.flush()).is_err() {
+            // The client went away; nothing left to serve
+            std::process::exit(0);
+        }
+    }
+
+    fn handle(&mut self, raw: &str) {
+        let message: Value = match serde_json::from_str(raw) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("mock-mcp-server: invalid JSON from client: {}", e);
+                let error = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+                });
+                self.write_message(&error.to_string());
+                return;
+            }
+        };
+        if self.replay.is_some() {
+            self.handle_replay(&message);
+            return;
+        }
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to a request we never sent
+            return;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let id = message.get("id").cloned();
+
+        let reply = self.dispatch(method, &params);
+        let Some(id) = id else {
+            // Notifications are never answered
+            return;
+        };
+        let response = match reply {
+            Reply::Result(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Reply::Error(code, message) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+            Reply::Raw(output) => {
+                self.write_raw(&output);
+                return;
+            }
+            Reply::None => return,
+        };
+        self.write_message(&response.to_string());
+
+        self.answered += 1;
+        if self.scenario.crash_after == Some(self.answered) {
+            eprintln!("mock-mcp-server: crashing after {} requests", self.answered);
+            std::process::exit(1);
+        }
+    }
+
+    fn handle_replay(&mut self, message: &Value) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        match replay.answer(message) {
+            Some(replies) => {
+                for reply in replies {
+                    self.write_recorded(&reply);
+                }
+            }
+            None => {
+                let method = message.get("method").and_then(Value::as_str);
+                if let (Some(method), Some(id)) = (method, message.get("id")) {
+                    let error = json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": -32601,
+                            "message": format!("No matching request in the recording: {}", method),
+                        },
+                    });
+                    self.write_message(&error.to_string());
+                }
+            }
+        }
+    }
+
+    // Non-JSON output was recorded as a string and goes back out as it was
+    fn write_recorded(&mut self, message: &Value) {
+        match message {
+            Value::String(raw) => self.write_raw(raw),
+            _ => self.write_message(&message.to_string()),
+        }
+    }
+
+    fn write_raw(&mut self, output: &str) {
+        let mut out = self.stdout.lock();
+        if writeln!(out, "{}", output)
+            .and_then(|_| out.flush())
+            .is_err()
+        {
+            std::process::exit(0);
+        }
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Reply {
+        match method {
+            "initialize" => {
+                let version = params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or(PROTOCOL_VERSION);
+                Reply::Result(json!({
+                    "protocolVersion": version,
+                    "capabilities": {
+                        "tools": { "listChanged": false },
+                        "resources": { "listChanged": false, "subscribe": false },
+                        "prompts": { "listChanged": false },
+                    },
+                    "serverInfo": {
+                        "name": self.scenario.server_name.as_deref().unwrap_or("mock-mcp-server"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }))
+            }
+            "ping" => Reply::Result(json!({})),
+            "tools/list" => Reply::Result(json!({ "tools": self.tool_list() })),
+            "tools/call" => self.call_tool(params),
+            "resources/list" => {
+                let resources: Vec<Value> = self
+                    .scenario
+                    .resources
+                    .iter()
+                    .map(|r| json!({ "uri": r.uri, "name": r.name, "mimeType": r.mime_type }))
+                    .collect();
+                Reply::Result(json!({ "resources": resources }))
+            }
+            "resources/read" => {
+                let uri = params
+                    .get("uri")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                match self.scenario.resources.iter().find(|r| r.uri == uri) {
+                    Some(r) => Reply::Result(json!({
+                        "contents": [{ "uri": r.uri, "mimeType": r.mime_type, "text": r.text }],
+                    })),
+                    None => Reply::Error(-32002, format!("Resource not found: {}", uri)),
+                }
+            }
+            "prompts/list" => {
+                let prompts: Vec<Value> = self
+                    .scenario
+                    .prompts
+                    .iter()
+                    .map(|p| json!({ "name": p.name, "description": p.description }))
+                    .collect();
+                Reply::Result(json!({ "prompts": prompts }))
+            }
+            "prompts/get" => {
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                match self.scenario.prompts.iter().find(|p| p.name == name) {
+                    Some(p) => Reply::Result(json!({
+                        "description": p.description,
+                        "messages": [{
+                            "role": "user",
+                            "content": { "type": "text", "text": p.text },
+                        }],
+                    })),
+                    None => Reply::Error(-32602, format!("Unknown prompt: {}", name)),
+                }
+            }
+            _ if method.starts_with("notifications/") => Reply::None,
+            _ => Reply::Error(-32601, format!("Method not found: {}", method)),
+        }
+    }
+
+    fn tool_list(&self) -> Vec<Value> {
+        let mut tools: Vec<Value> = self
+            .scenario
+            .tools
+            .iter()
+            .map(|t| {
+                json!({
+                    "name": t.name,
+                    "description": t.description,
+                    "inputSchema": t.input_schema,
+                })
+            })
+            .collect();
+        tools.push(json!({
+            "name": "echo",
+            "description": "Returns its arguments as text.",
+            "inputSchema": { "type": "object" },
+        }));
+        tools.push(json!({
+            "name": "fault",
+            "description": "Applies the given faults, then echoes its arguments.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "faults": { "type": "array" } },
+            },
+        }));
+        tools
+    }
+
+    fn call_tool(&mut self, params: &Value) -> Reply {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let arguments = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let echo = json!([{ "type": "text", "text": arguments.to_string() }]);
+
+        let (faults, content, is_error) = match name {
+            "echo" => (Vec::new(), echo, false),
+            "fault" => match serde_json::from_value::<Vec<Fault>>(
+                arguments
+                    .get("faults")
+                    .cloned()
+                    .unwrap_or_else(|| json!([])),
+            ) {
+                Ok(faults) => (faults, echo, false),
+                Err(e) => return Reply::Error(-32602, format!("Invalid faults: {}", e)),
+            },
+            _ => match self.scenario.tools.iter().find(|t| t.name == name) {
+                Some(tool) => (
+                    tool.faults.clone(),
+                    tool.content.clone().unwrap_or(echo),
+                    tool.is_error,
+                ),
+                None => return Reply::Error(-32602, format!("Unknown tool: {}", name)),
+            },
+        };
+
+        for fault in faults {
+            match fault {
+                Fault::Delay { ms } => std::thread::sleep(Duration::from_millis(ms)),
+                Fault::Stderr { lines, text } => {
+                    let text = text.unwrap_or_else(|| format!("stderr output from {}", name));
+                    spam_stderr(&text, lines);
+                }
+                Fault::Malformed { output } => {
+                    return Reply::Raw(output.unwrap_or_else(|| "{not json".to_string()))
+                }
+                Fault::Error { code, message } => {
+                    return Reply::Error(
+                        code,
+                        message.unwrap_or_else(|| format!("Injected error in {}", name)),
+                    )
+                }
+                Fault::Hang => return Reply::None,
+                Fault::Crash { exit_code, stderr } => {
+                    eprintln!(
+                        "{}",
+                        stderr.unwrap_or_else(|| format!("mock-mcp-server: crashing in {}", name))
+                    );
+                    std::process::exit(exit_code);
+                }
+            }
+        }
+
+        Reply::Result(json!({ "content": content, "isError": is_error }))
+    }
+}
+
+fn spam_stderr(text: &str, lines: usize) {
+    let mut err = io::stderr().lock();
+    for i in 0..lines {
+        let _ = writeln!(err, "{} ({}/{})", text, i + 1, lines);
+    }
+}
+
+/// Reads one Content-Length framed message; `None` at end of input.
+fn read_framed(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn parse_args() -> Result<(Scenario, Framing, Option<Replay>), String> {
+    let mut args = std::env::args().skip(1);
+    let mut scenario = Scenario::default();
+    let mut framing = Framing::Ndjson;
+    let mut replay = None;
+    // Flags override the scenario file, wherever they appear
+    let mut overrides: Vec<(String, String)> = Vec::new();
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        match flag.as_str() {
+            "--scenario" => {
+                let text = std::fs::read_to_string(&value)
+                    .map_err(|e| format!("Failed to read scenario {}: {}", value, e))?;
+                scenario = serde_json::from_str(&text)
+                    .map_err(|e| format!("Invalid scenario {}: {}", value, e))?;
+            }
+            "--replay" => replay = Some(Replay::load(&value)?),
+            "--framing" => {
+                framing = match value.as_str() {
+                    "ndjson" => Framing::Ndjson,
+                    "content-length" => Framing::ContentLength,
+                    _ => return Err(format!("Unknown framing: {}", value)),
+                }
+            }
+            _ => overrides.push((flag, value)),
+        }
+    }
+
+    for (flag, value) in overrides {
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+        };
+        match flag.as_str() {
+            "--banner" => scenario.banner = Some(value.clone()),
+            "--stderr-spam" => scenario.stderr_spam = number()? as usize,
+            "--startup-delay-ms" => scenario.startup_delay_ms = number()?,
+            "--crash-after" => scenario.crash_after = Some(number()? as usize),
+            _ => return Err(format!("Unknown option: {}", flag)),
+        }
+    }
+    Ok((scenario, framing, replay))
+}
+
+fn main() {
+    let (scenario, framing, replay) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("mock-mcp-server: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if scenario.startup_delay_ms > 0 {
+        std::thread::sleep(Duration::from_millis(scenario.startup_delay_ms));
+    }
+    if scenario.stderr_spam > 0 {
+        spam_stderr("mock-mcp-server: startup noise", scenario.stderr_spam);
+    }
+
+    let mut server = Server {
+        scenario,
+        framing,
+        stdout: io::stdout(),
+        answered: 0,
+        replay,
+    };
+    if let Some(banner) = server.scenario.banner.clone() {
+        server.write_raw(&banner);
+    }
+    if let Some(replay) = server.replay.as_mut() {
+        for message in replay.server_messages() {
+            server.write_recorded(&message);
+        }
+    }
+
+    let mut reader = BufReader::new(io::stdin());
+    loop {
+        let message = match framing {
+            Framing::Ndjson => {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) => None,
+                    Ok(_) => Some(line),
+                    Err(e) => {
+                        eprintln!("mock-mcp-server: failed to read stdin: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Framing::ContentLength => match read_framed(&mut reader) {
+                Ok(message) => message,
+                Err(e) => {
+                    eprintln!("mock-mcp-server: failed to read stdin: {}", e);
+                    std::process::exit(1);
+                }
+            },
+        };
+        let Some(message) = message else {
+            // stdin closed: the client asked us to shut down
+            break;
+        };
+        let message = message.trim();
+        if !message.is_empty() {
+            server.handle(message);
+        }
+    }
+}
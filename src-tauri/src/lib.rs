@@ -1,12 +1,19 @@
-use crate::mcp::control::ProcessRegistry;
+use crate::mcp::control::{shutdown_all, ProcessRegistry, APP_EXIT_DEADLINE};
+use crate::mcp::inspector::TrafficInspector;
+use crate::mcp::remote::ConnectionRegistry;
+use tauri::Manager;
 
-mod mcp;
+pub mod mcp;
 mod miniapp;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // One inspector sees the traffic of processes and remote connections alike
+    let inspector = TrafficInspector::default();
     tauri::Builder::default()
-        .manage(ProcessRegistry::default()) // Add the state
+        .manage(ProcessRegistry::default().with_inspector(inspector.clone())) // Add the state
+        .manage(ConnectionRegistry::default().with_inspector(inspector.clone()))
+        .manage(inspector)
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -20,8 +27,31 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             mcp::cmd::start_external_process,
             mcp::cmd::send_message_to_process,
-            mcp::cmd::stop_external_process
+            mcp::cmd::stop_external_process,
+            mcp::cmd::attach_process_events,
+            mcp::cmd::list_external_processes,
+            mcp::cmd::get_process_logs,
+            mcp::cmd::detect_available_runtimes,
+            mcp::cmd::import_mcp_servers,
+            mcp::cmd::mcp_initialize,
+            mcp::cmd::mcp_list_tools,
+            mcp::cmd::mcp_call_tool,
+            mcp::cmd::start_remote_connection,
+            mcp::cmd::send_message_to_connection,
+            mcp::cmd::stop_remote_connection,
+            mcp::cmd::list_remote_connections,
+            mcp::cmd::inspector_query,
+            mcp::cmd::inspector_subscribe,
+            mcp::cmd::inspector_unsubscribe,
+            mcp::cmd::inspector_clear
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                // kill_on_drop never fires for children still held in the registry
+                let registry = app_handle.state::<ProcessRegistry>().inner().clone();
+                tauri::async_runtime::block_on(shutdown_all(&registry, APP_EXIT_DEADLINE));
+            }
+        });
 }
@@ -0,0 +1,274 @@
+use crate::mcp::control::ProcessRegistry;
+use crate::mcp::writer::StdinWriter;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, OnceCell};
+
+/// Protocol revision sent in `initialize`; servers answer with the one they speak.
+pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// Default limit for a single request made by the native client.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Prefix of ids used by the native client, so its responses can be told apart from
+// the ones the frontend SDK is waiting for on the same process
+const REQUEST_ID_PREFIX: &str = "daan-rs-";
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Requests sent by the native client that are waiting for a response.
+#[derive(Clone, Default)]
+pub struct PendingRequests(Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>);
+
+impl PendingRequests {
+    fn register(&self, id: String) -> oneshot::Receiver<Value> {
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut pending) = self.0.lock() {
+            pending.insert(id, sender);
+        }
+        receiver
+    }
+
+    fn cancel(&self, id: &str) {
+        if let Ok(mut pending) = self.0.lock() {
+            pending.remove(id);
+        }
+    }
+
+    /// Fails every waiting request, e.g. because the process exited.
+    pub fn clear(&self) {
+        if let Ok(mut pending) = self.0.lock() {
+            pending.clear();
+        }
+    }
+
+    /// Hands a stdout message to the native request it answers. Returns `false` if
+    /// it is not a response to one of ours, so it should go to the frontend.
+    pub fn try_resolve(&self, message: &Value) -> bool {
+        // Responses have an id but no method; requests from the server have both
+        if message.get("method").is_some() {
+            return false;
+        }
+        let Some(id) = message
+            .get("id")
+            .and_then(Value::as_str)
+            .filter(|id| id.starts_with(REQUEST_ID_PREFIX))
+        else {
+            return false;
+        };
+        let sender = match self.0.lock() {
+            Ok(mut pending) => pending.remove(id),
+            Err(_) => None,
+        };
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(message.clone());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Result of the `initialize` handshake, kept for the lifetime of one process run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    pub protocol_version: String,
+    #[serde(default)]
+    pub capabilities: Value,
+    #[serde(default)]
+    pub server_info: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+impl InitializeResult {
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.get(capability).is_some()
+    }
+}
+
+/// Handshake state of a process. Replaced when the process restarts, since the new
+/// run has to be initialized again.
+pub type McpSession = Arc<OnceCell<InitializeResult>>;
+
+/// JSON-RPC client speaking MCP to a process in the `ProcessRegistry`.
+///
+/// Writes go through the process's `StdinWriter`; responses are picked out of its
+/// stdout by `PendingRequests::try_resolve` before anything is forwarded to the
+/// frontend.
+pub struct McpClient {
+    process_id: String,
+    registry: ProcessRegistry,
+}
+
+impl McpClient {
+    pub fn new(registry: ProcessRegistry, process_id: impl Into<String>) -> Self {
+        Self {
+            process_id: process_id.into(),
+            registry,
+        }
+    }
+
+    fn connection(&self) -> Result<(StdinWriter, PendingRequests, McpSession), String> {
+        let guard = self
+            .registry
+            .lock()
+            .map_err(|_| "Mutex poisoned".to_string())?;
+        let managed_process = guard
+            .get(&self.process_id)
+            .ok_or_else(|| format!("Process with ID {} not found.", self.process_id))?;
+        let writer = managed_process
+            .writer
+            .clone()
+            .ok_or_else(|| format!("Stdin for process {} was not available.", self.process_id))?;
+        Ok((
+            writer,
+            managed_process.pending.clone(),
+            managed_process.session.clone(),
+        ))
+    }
+
+    /// Sends a raw request without making sure the session is initialized.
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let (writer, pending, _) = self.connection()?;
+        let id = format!(
+            "{}{}",
+            REQUEST_ID_PREFIX,
+            NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut message = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+
+        let response = pending.register(id.clone());
+        if let Err(e) = writer.send(message.to_string()).await {
+            pending.cancel(&id);
+            return Err(e);
+        }
+
+        let response = match tokio::time::timeout(timeout, response).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => {
+                return Err(format!(
+                    "Process {} exited before answering '{}'.",
+                    self.process_id, method
+                ))
+            }
+            Err(_) => {
+                pending.cancel(&id);
+                // Let the server stop working on it; failure to deliver is not interesting
+                let cancel = json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/cancelled",
+                    "params": { "requestId": id, "reason": "Request timed out" },
+                });
+                let _ = writer.send(cancel.to_string()).await;
+                return Err(format!(
+                    "Request '{}' timed out after {:?}.",
+                    method, timeout
+                ));
+            }
+        };
+
+        if let Some(error) = response.get("error") {
+            return Err(format!(
+                "MCP error {}: {}",
+                error.get("code").unwrap_or(&Value::Null),
+                error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+            ));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    pub async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), String> {
+        let (writer, _, _) = self.connection()?;
+        let mut message = json!({ "jsonrpc": "2.0", "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        writer.send(message.to_string()).await
+    }
+
+    /// Performs the `initialize` handshake once per process run and returns its result.
+    pub async fn initialize(&self) -> Result<InitializeResult, String> {
+        let (_, _, session) = self.connection()?;
+        session
+            .get_or_try_init(|| async {
+                let params = json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "Daan", "version": env!("CARGO_PKG_VERSION") },
+                });
+                let result = self
+                    .request("initialize", Some(params), DEFAULT_REQUEST_TIMEOUT)
+                    .await?;
+                let result: InitializeResult = serde_json::from_value(result)
+                    .map_err(|e| format!("Invalid initialize result: {}", e))?;
+                self.notify("notifications/initialized", None).await?;
+                println!(
+                    "Initialized MCP session with process {} (protocol {}).",
+                    self.process_id, result.protocol_version
+                );
+                Ok(result)
+            })
+            .await
+            .cloned()
+    }
+
+    /// Lists every tool of the server, following pagination cursors.
+    pub async fn list_tools(&self) -> Result<Vec<Value>, String> {
+        let session = self.initialize().await?;
+        if !session.supports("tools") {
+            return Ok(Vec::new());
+        }
+
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.as_ref().map(|cursor| json!({ "cursor": cursor }));
+            let page = self
+                .request("tools/list", params, DEFAULT_REQUEST_TIMEOUT)
+                .await?;
+            if let Some(page_tools) = page.get("tools").and_then(Value::as_array) {
+                tools.extend(page_tools.iter().cloned());
+            }
+            cursor = page
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        self.initialize().await?;
+        self.request(
+            "tools/call",
+            Some(json!({ "name": name, "arguments": arguments })),
+            timeout,
+        )
+        .await
+    }
+}
@@ -1,200 +1,240 @@
-use std::process::Stdio;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::{
-    mcp::control::{emit_event, handle_stdout, monitor_process, ManagedProcess},
+    mcp::client::{InitializeResult, McpClient, PendingRequests, DEFAULT_REQUEST_TIMEOUT},
+    mcp::control::{
+        attach_child_io, monitor_process, spawn_child, ManagedProcess, ProcessInfo,
+        ProcessOptions,
+    },
+    mcp::events::{ProcessEvent, ProcessEvents},
+    mcp::import::{
+        import_servers, known_config_paths, source_for_path, ExistingServer, ImportReport,
+    },
+    mcp::inspector::{InspectorUpdate, TrafficInspector, TrafficPage, TrafficQuery, TrafficTap},
+    mcp::logs::{LogLine, LogStream, ProcessLog},
+    mcp::recorder::TrafficRecorder,
+    mcp::remote::{
+        start_connection, ConnectionRegistry, OutgoingMessage, RemoteConnectionInfo,
+        RemoteOptions, RemoteTransport,
+    },
+    mcp::resolve::{resolve_command, ResolvedCommand},
+    mcp::runtimes::{detect_runtimes, RuntimeInfo},
     ProcessRegistry,
 };
+use serde_json::Value;
+use tauri::ipc::Channel;
 use tauri::AppHandle;
+use tauri::Manager;
 use tauri::State;
-use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    process::{Child, ChildStdin},
-};
+use tokio::sync::oneshot;
 use uuid::Uuid;
 
+/// Subdirectory recordings go to when `ProcessOptions::record_dir` is unset.
+const TRACES_DIR: &str = "mcp-traces";
+
 async fn spawn_and_manage_process_internal(
     command_str: String,
     args_vec: Vec<String>,
-    app_handle: &AppHandle,                      // Pass as reference
-    registry_state: &State<'_, ProcessRegistry>, // Pass as reference
+    resolved: ResolvedCommand,
+    options: &ProcessOptions,
+    events: &ProcessEvents,
+    registry: &ProcessRegistry,
 ) -> Result<String, std::io::Error> {
-    // Return std::io::Error to check kind
-    println!("Internal spawn: {} with args {:?}", command_str, args_vec);
-
-    let mut cmd = tokio::process::Command::new(&command_str);
-    cmd.args(&args_vec)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .kill_on_drop(true);
-
-    // Conditional compilation for Windows-specific settings if needed
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
-    }
+    println!(
+        "Internal spawn: {} with args {:?}",
+        resolved.program.display(),
+        resolved.args
+    );
 
-    match cmd.spawn() {
+    match spawn_child(&resolved, options) {
         Ok(mut child) => {
             let process_id = Uuid::new_v4().to_string();
             println!("Process started successfully with ID: {}", process_id);
 
-            let stdin = child.stdin.take().ok_or_else(|| {
-                std::io::Error::new(std::io::ErrorKind::Other, "Failed to capture stdin")
-            })?;
-            let stdout = child.stdout.take().ok_or_else(|| {
-                std::io::Error::new(std::io::ErrorKind::Other, "Failed to capture stdout")
-            })?;
-            let stderr = child.stderr.take().ok_or_else(|| {
-                std::io::Error::new(std::io::ErrorKind::Other, "Failed to capture stderr")
-            })?;
+            let log = ProcessLog::default();
+            let pending = PendingRequests::default();
+            let events = events.clone();
+            let recorder = options
+                .record_traffic
+                .then(|| create_recorder(options, &process_id, &log))
+                .flatten();
+            let tap = TrafficTap::new(process_id.clone())
+                .with_recorder(recorder)
+                .with_inspector(registry.inspector().clone());
+            let writer = attach_child_io(
+                &mut child,
+                &process_id,
+                options,
+                &log,
+                &pending,
+                &events,
+                &tap,
+            )?;
 
-            let managed_process = ManagedProcess::new(child).with_stdin(stdin);
+            let managed_process = ManagedProcess::new(child, events.clone())
+                .with_writer(writer)
+                .with_log(log)
+                .with_pending(pending)
+                .with_tap(tap)
+                .with_command(command_str, args_vec)
+                .with_resolved(resolved)
+                .with_options(options.clone());
 
-            registry_state
+            registry
                 .lock()
                 .unwrap()
                 .insert(process_id.clone(), managed_process);
             println!("Process {} added to registry.", process_id);
 
-            // Spawn task to read stdout
-            let stdout_handle = app_handle.clone();
-            let stdout_pid = process_id.clone();
-            tokio::spawn(async move {
-                let reader = BufReader::new(stdout);
-                handle_stdout(reader, stdout_pid, stdout_handle).await;
-            });
-            println!("Spawned stdout handler task for process {}.", process_id);
-
-            // Spawn task to read stderr
-            let stderr_handle = app_handle.clone();
-            let stderr_pid = process_id.clone();
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(stderr);
-                let mut line = String::new();
-                loop {
-                    match reader.read_line(&mut line).await {
-                        Ok(0) => break, // EOF
-                        Ok(_) => {
-                            eprintln!("[Process {} stderr]: {}", stderr_pid, line.trim());
-                            emit_event(
-                                &format!("process_stderr_{}", stderr_pid),
-                                line.trim().to_string(),
-                                &stderr_handle,
-                            );
-                            line.clear();
-                        }
-                        Err(e) => {
-                            eprintln!("Error reading stderr for {}: {}", stderr_pid, e);
-                            break;
-                        }
-                    }
-                }
-                println!("Stderr handler task finished for {}.", stderr_pid);
-            });
-            println!("Spawned stderr handler task for process {}.", process_id);
-
             // Spawn task to monitor process completion
             // Clone the Arc<Mutex<...>> for the monitor task
-            let monitor_registry_clone = registry_state.inner().clone();
-            let monitor_handle = app_handle.clone();
+            let monitor_registry_clone = registry.clone();
             let monitor_pid = process_id.clone();
 
             tokio::spawn(async move {
-                monitor_process(monitor_pid, monitor_handle, monitor_registry_clone).await;
+                monitor_process(monitor_pid, events, monitor_registry_clone).await;
             });
             println!("Spawned process monitor task for process {}.", process_id);
 
             Ok(process_id)
         }
+        Err(e) => Err(e),
+    }
+}
+
+// Recording is best effort: a process that cannot be recorded still runs
+fn create_recorder(
+    options: &ProcessOptions,
+    process_id: &str,
+    log: &ProcessLog,
+) -> Option<TrafficRecorder> {
+    let dir = options
+        .record_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join(TRACES_DIR));
+    match TrafficRecorder::create(&dir, process_id) {
+        Ok(recorder) => {
+            log.push(
+                LogStream::System,
+                format!("Recording traffic to {}", recorder.path().display()),
+            );
+            Some(recorder)
+        }
         Err(e) => {
-            // Don't print here, let the caller decide based on whether it's a retry
-            Err(e)
+            eprintln!(
+                "Failed to start recording for process {} in {}: {}",
+                process_id,
+                dir.display(),
+                e
+            );
+            log.push(LogStream::System, format!("Failed to start recording: {}", e));
+            None
         }
     }
 }
 
+/// Starts a process and returns its id. Its output and lifecycle events are sent
+/// to `on_event` from the moment it is spawned.
 #[tauri::command]
 pub async fn start_external_process(
     command: String,
     args: Vec<String>,
+    options: Option<ProcessOptions>,
+    on_event: Channel<ProcessEvent>,
     app_handle: AppHandle,
     registry: State<'_, ProcessRegistry>,
+) -> Result<String, String> {
+    let mut options = options.unwrap_or_default();
+    if options.record_traffic && options.record_dir.is_none() {
+        if let Ok(log_dir) = app_handle.path().app_log_dir() {
+            options.record_dir = Some(log_dir.join(TRACES_DIR).to_string_lossy().into_owned());
+        }
+    }
+    start_process(
+        command,
+        args,
+        options,
+        ProcessEvents::new(on_event),
+        &registry,
+    )
+    .await
+}
+
+/// Spawns `command` under the registry's management, without needing a running
+/// Tauri app. The command is resolved to an executable up front (see
+/// `resolve_command`), so a missing one fails with an explanation instead of a
+/// bare `NotFound`.
+pub async fn start_process(
+    command: String,
+    args: Vec<String>,
+    options: ProcessOptions,
+    events: ProcessEvents,
+    registry: &ProcessRegistry,
 ) -> Result<String, String> {
     println!(
         "Attempting to start process: {} with args {:?}",
         command, args
     );
 
-    // First attempt
-    match spawn_and_manage_process_internal(command.clone(), args.clone(), &app_handle, &registry)
+    options.validate()?;
+
+    // Resolving may wait on the login shell the first time
+    let resolving = {
+        let (command, args, options) = (command.clone(), args.clone(), options.clone());
+        tokio::task::spawn_blocking(move || resolve_command(&command, &args, &options))
+    };
+    let resolved = resolving
         .await
-    {
-        Ok(process_id) => Ok(process_id),
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                eprintln!(
-                    "Initial spawn failed (NotFound): {}. Attempting OS-specific retry.",
-                    e
-                );
-                 /* https://github.com/modelcontextprotocol/servers/issues/1526#issuecomment-2819858033 */
-                let (retry_command_str, retry_args_vec) = if cfg!(target_os = "windows") {
-                    let mut new_args = vec!["/c".to_string(), command.clone()];
-                    new_args.extend(args.clone()); // Add original args after original command
-                    ("cmd.exe".to_string(), new_args)
-                } else {
-                    // For sh -c "command arg1 arg2..."
-                    // We need to join the original command and its arguments into a single string.
-                    // Arguments containing spaces or special characters should be quoted.
-                    let mut cmd_parts = vec![command.clone()]; // Start with the command
-                    cmd_parts.extend(args.clone()); // Add all arguments
-
-                    // shell-words::join will handle quoting individual parts if they contain spaces etc.
-                    // and then join them with spaces. This is suitable for sh -c "..."
-                    let escaped_full_command = shell_words::join(&cmd_parts);
-                    (
-                        "sh".to_string(),
-                        vec!["-c".to_string(), escaped_full_command],
-                    )
-                };
-
-                println!(
-                    "Retrying with: {} and args {:?}",
-                    retry_command_str, retry_args_vec
-                );
-
-                // Second attempt with OS-specific shell
-                match spawn_and_manage_process_internal(
-                    retry_command_str.clone(),
-                    retry_args_vec.clone(),
-                    &app_handle,
-                    &registry,
-                )
-                .await
-                {
-                    Ok(process_id) => Ok(process_id),
-                    Err(e2) => {
-                        let err_msg = format!(
-                            "Failed to start process on retry (cmd: '{}', args: {:?}): {}",
-                            retry_command_str, retry_args_vec, e2
-                        );
-                        eprintln!("{}", err_msg);
-                        Err(err_msg)
-                    }
-                }
-            } else {
-                // Error was not NotFound, or retry also failed
-                let err_msg = format!(
-                    "Failed to start process (cmd: '{}', args: {:?}): {}",
-                    command, args, e
-                );
-                eprintln!("{}", err_msg);
-                Err(err_msg)
-            }
-        }
+        .map_err(|e| format!("Failed to resolve command '{}': {}", command, e))??;
+
+    spawn_and_manage_process_internal(
+        command.clone(),
+        args.clone(),
+        resolved,
+        &options,
+        &events,
+        registry,
+    )
+    .await
+    .map_err(|e| {
+        let err_msg = format!(
+            "Failed to start process (cmd: '{}', args: {:?}): {}",
+            command, args, e
+        );
+        eprintln!("{}", err_msg);
+        err_msg
+    })
+}
+
+/// Reports which common MCP server runtimes (Node.js, Python, uv, Deno, Bun,
+/// Docker) are available, resolved the way a server started with `options` would be.
+#[tauri::command]
+pub async fn detect_available_runtimes(
+    options: Option<ProcessOptions>,
+) -> Result<Vec<RuntimeInfo>, String> {
+    let options = options.unwrap_or_default();
+    options.validate()?;
+    Ok(detect_runtimes(&options).await)
+}
+
+/// Collects the MCP servers configured in other clients' config files, plus the
+/// `.vscode/mcp.json` of each workspace directory or file in `paths`. Entries that
+/// duplicate one of the `existing` servers are reported instead of returned.
+#[tauri::command]
+pub async fn import_mcp_servers(
+    paths: Option<Vec<String>>,
+    existing: Option<Vec<ExistingServer>>,
+) -> Result<ImportReport, String> {
+    let mut sources = known_config_paths();
+    for path in paths.unwrap_or_default() {
+        sources.push(source_for_path(std::path::Path::new(&path))?);
     }
+    let existing = existing.unwrap_or_default();
+    tokio::task::spawn_blocking(move || import_servers(&sources, &existing))
+        .await
+        .map_err(|e| format!("Failed to import MCP servers: {}", e))
 }
 
 #[tauri::command]
@@ -202,134 +242,286 @@ pub async fn send_message_to_process(
     id: String,
     message: String, // Assume message is already JSON stringified by frontend
     registry: State<'_, ProcessRegistry>,
+) -> Result<(), String> {
+    send_to_process(&registry, id, message).await
+}
+
+/// Writes `message` to the stdin of a managed process.
+pub async fn send_to_process(
+    registry: &ProcessRegistry,
+    id: String,
+    message: String,
 ) -> Result<(), String> {
     println!("Attempting to send message to process {}: {}", id, message);
 
-    // --- Step 1: Acquire lock, get stdin, release lock ---
-    let mut stdin_handle = {
-        // Create a scope for the MutexGuard
-        let mut lock = registry.lock().map_err(|_| "Mutex poisoned".to_string())?; // Handle potential poisoning
-
-        if let Some(managed_process) = lock.get_mut(&id) {
-            // Take the stdin handle out. managed_process.stdin will become None.
-            managed_process.stdin.take()
-        } else {
-            // Process not found in the registry
-            println!("Process {} not found in registry.", id);
-            return Err(format!("Process with ID {} not found.", id));
-        }
-        // MutexGuard `lock` is dropped here as it goes out of scope
-    }; // --- MutexGuard scope ends ---
+    // Clone the writer handle out so the lock is not held across the await.
+    // Ordering and exclusive access to stdin are handled by the writer task.
+    let writer = {
+        let lock = registry.lock().map_err(|_| "Mutex poisoned".to_string())?; // Handle potential poisoning
 
-    // --- Step 2: Perform async operation outside the lock ---
-    if let Some(mut stdin) = stdin_handle.take() {
-        // Take ownership of stdin from the Option
-        let mut msg_with_newline = message;
-        msg_with_newline.push('\n');
+        match lock.get(&id) {
+            Some(managed_process) => managed_process.writer.clone(),
+            None => {
+                // Process not found in the registry
+                println!("Process {} not found in registry.", id);
+                return Err(format!("Process with ID {} not found.", id));
+            }
+        }
+    };
 
-        let write_result = stdin.write_all(msg_with_newline.as_bytes()).await;
+    let Some(writer) = writer else {
+        // Stop requested, or the process is between restarts
+        return Err(format!("Stdin for process {} was not available.", id));
+    };
 
-        // --- Step 3: Re-acquire lock to potentially put stdin back ---
-        {
-            // Create a new scope for the second lock
-            let mut lock = registry
-                .lock()
-                .map_err(|_| "Mutex poisoned while trying to return stdin".to_string())?;
-
-            // Check if the process *still* exists in the registry
-            if let Some(managed_process) = lock.get_mut(&id) {
-                // Put the stdin handle back into the managed process only if write was successful
-                // If write failed, stdin might be in a bad state, maybe log and drop it?
-                // Or always try to put it back? Let's put it back for now.
-                if managed_process.stdin.is_none() {
-                    // Avoid overwriting if another thread somehow put one back
-                    managed_process.stdin = Some(stdin);
-                } else {
-                    eprintln!("Stdin for process {} was unexpectedly present when trying to return handle.", id);
-                    // Drop the handle we took earlier as we cannot put it back
-                    drop(stdin);
-                }
-            } else {
-                // The process was removed from the registry (e.g., stopped) while we were writing.
-                // The stdin handle we have will be dropped naturally when `stdin` goes out of scope.
-                eprintln!(
-                    "Process {} was removed while sending message. Stdin handle will be dropped.",
-                    id
-                );
-            }
-            // Second MutexGuard `lock` is dropped here
-        } // --- Second MutexGuard scope ends ---
-
-        // Now handle the result of the write operation
-        match write_result {
-            Ok(_) => {
-                println!("Message sent successfully to process {}.", id);
-                Ok(())
-            }
-            Err(e) => {
-                eprintln!("Failed to write to stdin for process {}: {}", id, e);
-                Err(format!("Failed to write to stdin: {}", e))
-            }
+    match writer.send(message).await {
+        Ok(()) => {
+            println!("Message sent successfully to process {}.", id);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Failed to send message to process {}: {}", id, e);
+            Err(e)
         }
-    } else {
-        // This means stdin was None when we first checked (either never existed or already taken)
-        Err(format!("Stdin for process {} was not available.", id))
     }
 }
 
 #[tauri::command]
 pub async fn stop_external_process(
     id: String,
+    grace_period_ms: Option<u64>,
     registry: State<'_, ProcessRegistry>,
     // app_handle: AppHandle,
+) -> Result<(), String> {
+    stop_process(&registry, &id, grace_period_ms.map(Duration::from_millis))
+}
+
+/// Asks a managed process to shut down; its `Closed` event follows once it exited.
+pub fn stop_process(
+    registry: &ProcessRegistry,
+    id: &str,
+    grace_period: Option<Duration>,
 ) -> Result<(), String> {
     println!("Attempting to stop process {}", id);
 
-    // --- Step 1: Lock, remove process, get Child, unlock ---
-    let child_to_kill: Option<Child> = {
-        // Scope for the lock guard
-        let lock_result = registry.lock();
-        match lock_result {
-            Ok(mut guard) => {
-                // Remove the process from the registry
-                guard
-                    .remove(&id)
-                    // into_child now returns Option<Child>
-                    .and_then(|managed_process| managed_process.into_child())
-            }
-            Err(poison_error) => {
-                eprintln!(
-                    "Mutex poisoned when trying to stop process {}: {}",
-                    id, poison_error
-                );
-                return Err(format!("Mutex poisoned: {}", poison_error));
+    // The monitor task owns the Child, so we only flag the entry and signal it.
+    // The entry stays in the registry (state: exiting) until the monitor sees the exit.
+    let mut lock = registry.lock().map_err(|poison_error| {
+        eprintln!(
+            "Mutex poisoned when trying to stop process {}: {}",
+            id, poison_error
+        );
+        format!("Mutex poisoned: {}", poison_error)
+    })?;
+
+    match lock.get_mut(id) {
+        Some(managed_process) => {
+            if managed_process.request_stop(grace_period) {
+                println!("Shutdown requested for process {}.", id);
+            } else {
+                println!("Process {} is already being stopped.", id);
             }
+            Ok(())
         }
-    }; // --- Lock guard scope ends ---
-
-    // --- Step 2: Kill the process outside the lock ---
-    if let Some(mut child) = child_to_kill {
-        match child.kill().await {
-            Ok(_) => {
-                println!("Kill signal sent successfully to process {}.", id);
-                // The monitor task might still be running briefly, but it won't find the
-                // entry when it tries to remove it later, which is fine.
-                Ok(())
-            }
-            Err(e) => {
-                eprintln!("Failed to send kill signal to process {}: {}", id, e);
-                Err(format!("Failed to kill process: {}", e))
+        None => {
+            eprintln!("Process {} not found when stopping.", id);
+            Err(format!("Process with ID {} not found.", id))
+        }
+    }
+}
+
+/// Redirects the events of a running process to `on_event`, e.g. for a webview
+/// that reloaded and lost the channel it started the process with.
+#[tauri::command]
+pub async fn attach_process_events(
+    id: String,
+    on_event: Channel<ProcessEvent>,
+    registry: State<'_, ProcessRegistry>,
+) -> Result<(), String> {
+    let lock = registry.lock().map_err(|_| "Mutex poisoned".to_string())?;
+    let managed_process = lock
+        .get(&id)
+        .ok_or_else(|| format!("Process with ID {} not found.", id))?;
+    managed_process.events.replace(on_event);
+    println!("Attached a new event channel to process {}.", id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_external_processes(
+    registry: State<'_, ProcessRegistry>,
+) -> Result<Vec<ProcessInfo>, String> {
+    let lock = registry.lock().map_err(|_| "Mutex poisoned".to_string())?;
+    let mut processes: Vec<ProcessInfo> = lock
+        .iter()
+        .map(|(id, managed_process)| managed_process.info(id))
+        .collect();
+    processes.sort_by_key(|info| info.started_at);
+    Ok(processes)
+}
+
+#[tauri::command]
+pub async fn get_process_logs(
+    id: String,
+    limit: Option<usize>,
+    registry: State<'_, ProcessRegistry>,
+) -> Result<Vec<LogLine>, String> {
+    registry
+        .process_log(&id)
+        .map(|log| log.tail(limit))
+        .ok_or_else(|| format!("No logs found for process with ID {}.", id))
+}
+
+#[tauri::command]
+pub async fn mcp_initialize(
+    id: String,
+    registry: State<'_, ProcessRegistry>,
+) -> Result<InitializeResult, String> {
+    McpClient::new(registry.inner().clone(), id).initialize().await
+}
+
+#[tauri::command]
+pub async fn mcp_list_tools(
+    id: String,
+    registry: State<'_, ProcessRegistry>,
+) -> Result<Vec<Value>, String> {
+    McpClient::new(registry.inner().clone(), id).list_tools().await
+}
+
+#[tauri::command]
+pub async fn mcp_call_tool(
+    id: String,
+    name: String,
+    arguments: Option<Value>,
+    timeout_ms: Option<u64>,
+    registry: State<'_, ProcessRegistry>,
+) -> Result<Value, String> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+    McpClient::new(registry.inner().clone(), id)
+        .call_tool(
+            &name,
+            arguments.unwrap_or_else(|| Value::Object(Default::default())),
+            timeout,
+        )
+        .await
+}
+
+#[tauri::command]
+pub async fn start_remote_connection(
+    transport: RemoteTransport,
+    url: String,
+    options: Option<RemoteOptions>,
+    on_event: Channel<ProcessEvent>,
+    registry: State<'_, ConnectionRegistry>,
+) -> Result<String, String> {
+    println!("Attempting to connect to {} over {:?}", url, transport);
+    start_connection(
+        transport,
+        &url,
+        &options.unwrap_or_default(),
+        ProcessEvents::new(on_event),
+        registry.inner(),
+    )
+}
+
+#[tauri::command]
+pub async fn send_message_to_connection(
+    id: String,
+    message: String,
+    registry: State<'_, ConnectionRegistry>,
+) -> Result<(), String> {
+    let (outgoing, tap) = {
+        let guard = registry.lock().map_err(|_| "Mutex poisoned".to_string())?;
+        guard
+            .get(&id)
+            .map(|connection| (connection.outgoing(), connection.tap()))
+            .ok_or_else(|| format!("Connection with ID {} not found.", id))?
+    };
+
+    tap.outbound(&message);
+    let (reply, result) = oneshot::channel();
+    outgoing
+        .send(OutgoingMessage { message, reply })
+        .await
+        .map_err(|_| format!("Connection {} is closed.", id))?;
+    result
+        .await
+        .map_err(|_| format!("Connection {} closed before the message was sent.", id))?
+}
+
+#[tauri::command]
+pub async fn stop_remote_connection(
+    id: String,
+    registry: State<'_, ConnectionRegistry>,
+) -> Result<(), String> {
+    let mut guard = registry.lock().map_err(|_| "Mutex poisoned".to_string())?;
+    match guard.get_mut(&id) {
+        Some(connection) => {
+            // The entry is removed once the transport task has closed the connection
+            if !connection.request_stop() {
+                println!("Stop already requested for connection {}.", id);
             }
+            Ok(())
         }
+        None => Err(format!("Connection with ID {} not found.", id)),
+    }
+}
+
+#[tauri::command]
+pub async fn list_remote_connections(
+    registry: State<'_, ConnectionRegistry>,
+) -> Result<Vec<RemoteConnectionInfo>, String> {
+    let guard = registry.lock().map_err(|_| "Mutex poisoned".to_string())?;
+    let mut connections: Vec<RemoteConnectionInfo> = guard
+        .iter()
+        .map(|(id, connection)| connection.info(id))
+        .collect();
+    connections.sort_by_key(|connection| connection.started_at);
+    Ok(connections)
+}
+
+/// Returns a page of captured exchanges, newest first.
+#[tauri::command]
+pub async fn inspector_query(
+    query: Option<TrafficQuery>,
+    inspector: State<'_, TrafficInspector>,
+) -> Result<TrafficPage, String> {
+    Ok(inspector.query(&query.unwrap_or_default()))
+}
+
+/// Streams new and completed exchanges of `source_id` (all servers if unset) to
+/// `on_update`. Returns the id to pass to `inspector_unsubscribe`.
+#[tauri::command]
+pub async fn inspector_subscribe(
+    source_id: Option<String>,
+    on_update: Channel<InspectorUpdate>,
+    inspector: State<'_, TrafficInspector>,
+) -> Result<u64, String> {
+    Ok(inspector.subscribe(source_id, on_update))
+}
+
+#[tauri::command]
+pub async fn inspector_unsubscribe(
+    subscription_id: u64,
+    inspector: State<'_, TrafficInspector>,
+) -> Result<(), String> {
+    if inspector.unsubscribe(subscription_id) {
+        Ok(())
     } else {
-        // Process was not found OR ManagedProcess existed but child was already taken (e.g., by monitor)
-        eprintln!(
-            "Process {} not found or child handle already taken when stopping.",
-            id
-        );
         Err(format!(
-            "Process with ID {} not found or already being monitored/stopped.",
-            id
+            "Inspector subscription {} not found.",
+            subscription_id
         ))
     }
 }
+
+#[tauri::command]
+pub async fn inspector_clear(
+    source_id: Option<String>,
+    inspector: State<'_, TrafficInspector>,
+) -> Result<(), String> {
+    inspector.clear(source_id.as_deref());
+    Ok(())
+}
@@ -1,28 +1,267 @@
+use crate::mcp::client::{McpSession, PendingRequests};
+use crate::mcp::events::{ExitInfo, ProcessEvent, ProcessEvents};
+use crate::mcp::framing::{
+    Decoded, Framing, FramingMode, MessageDecoder, DEFAULT_MAX_MESSAGE_SIZE,
+};
+use crate::mcp::inspector::{TrafficInspector, TrafficTap};
+use crate::mcp::limits::{ResourceLimit, ResourceLimits};
+use crate::mcp::logs::{ExitedLog, LogStream, ProcessLog, EXITED_LOG_RETENTION};
+use crate::mcp::resolve::ResolvedCommand;
+use crate::mcp::sandbox::SandboxProfile;
+use crate::mcp::supervisor::{RestartPolicy, RestartingEvent, RESTART_RESET_AFTER};
+use crate::mcp::writer::{StdinWriter, DEFAULT_WRITE_TIMEOUT};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::process::Stdio;
 use std::ops::{Deref, DerefMut};
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager, State, Window}; // Ensure Manager is imported
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout};
+use tokio::sync::oneshot;
 use uuid::Uuid;
 
+/// How the spawned process should treat the environment of the Daan process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EnvPolicy {
+    /// Start from the parent environment and overlay the configured variables.
+    #[default]
+    Inherit,
+    /// Start from an empty environment; only the configured variables are set.
+    Clear,
+}
+
+/// Per-process spawn settings sent by the frontend along with `command`/`args`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessOptions {
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub env_policy: EnvPolicy,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// How long to wait after each shutdown stage before escalating.
+    #[serde(default)]
+    pub shutdown_grace_period_ms: Option<u64>,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Limit for queueing and writing a single message to stdin.
+    #[serde(default)]
+    pub write_timeout_ms: Option<u64>,
+    /// How messages are delimited on stdin/stdout.
+    #[serde(default)]
+    pub framing: FramingMode,
+    /// Larger messages on stdout are dropped with an error.
+    #[serde(default)]
+    pub max_message_size: Option<usize>,
+    /// Record every message to and from the process to a JSONL file.
+    #[serde(default)]
+    pub record_traffic: bool,
+    /// Directory for recordings; the app's log directory if unset.
+    #[serde(default)]
+    pub record_dir: Option<String>,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// Confine the process; it runs with the user's full privileges if unset.
+    #[serde(default)]
+    pub sandbox: Option<SandboxProfile>,
+    /// Hand the command to the platform shell (`sh -c` / `cmd.exe /c`) instead of
+    /// running it directly, for commands that rely on aliases, functions or pipes.
+    #[serde(default)]
+    pub run_in_shell: bool,
+}
+
+impl ProcessOptions {
+    pub fn write_timeout(&self) -> Duration {
+        self.write_timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_WRITE_TIMEOUT)
+    }
+
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+            .filter(|size| *size > 0)
+            .unwrap_or(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    pub fn shutdown_grace_period(&self) -> Duration {
+        self.shutdown_grace_period_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_SHUTDOWN_GRACE_PERIOD)
+    }
+
+    /// Applies environment and working directory settings to a command before spawning.
+    pub fn apply_to(&self, cmd: &mut tokio::process::Command) {
+        if self.env_policy == EnvPolicy::Clear {
+            cmd.env_clear();
+        }
+        cmd.envs(&self.env);
+        if let Some(cwd) = self.cwd.as_deref().filter(|dir| !dir.trim().is_empty()) {
+            cmd.current_dir(cwd);
+        }
+    }
+
+    /// Checks settings that would otherwise surface as a confusing spawn error
+    /// (a missing `cwd` is reported by the OS as `NotFound`, same as a missing binary).
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(cwd) = self.cwd.as_deref().filter(|dir| !dir.trim().is_empty()) {
+            if !std::path::Path::new(cwd).is_dir() {
+                return Err(format!("Working directory '{}' does not exist.", cwd));
+            }
+        }
+        if let Some(key) = self
+            .env
+            .keys()
+            .find(|key| key.is_empty() || key.contains('=') || key.contains('\0'))
+        {
+            return Err(format!("Invalid environment variable name: '{}'", key));
+        }
+        self.limits.validate()?;
+        match &self.sandbox {
+            Some(sandbox) => sandbox.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Default wait between shutdown stages (stdin closed -> SIGTERM -> SIGKILL).
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Stages of the graceful shutdown, emitted as `process_shutdown_<id>` events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShutdownStage {
+    StdinClosed,
+    Terminate,
+    Kill,
+}
+
+/// Lifecycle state of a registered process as reported to the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessState {
+    Running,
+    /// Exited on its own and is waiting out the backoff before being started again.
+    Restarting,
+    /// A stop was requested; the entry is removed once the monitor sees the exit.
+    Exiting,
+}
+
+/// Snapshot of a `ManagedProcess` returned by `list_external_processes`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessInfo {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Executable that was actually started for `command`.
+    pub resolved_path: String,
+    pub pid: Option<u32>,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    pub state: ProcessState,
+    pub restart_count: u32,
+    /// Stdout lines that were not JSON-RPC messages, e.g. banners; they only
+    /// went to the process log.
+    pub ignored_stdout_lines: u64,
+    /// File the traffic is recorded to, if recording.
+    pub recording_path: Option<String>,
+}
+
 pub struct ManagedProcess {
     pub child: Option<Child>,
-    // We need to wrap stdin/stdout in Option because they are taken when used
-    pub stdin: Option<ChildStdin>,
+    // Stdin is owned by a writer task; dropping the handle closes it
+    pub writer: Option<StdinWriter>,
     // We don't store stdout reader here, it's handled in a separate task
+    pub command: String,
+    pub args: Vec<String>,
+    // What `command` resolved to at start; restarts run the same executable
+    pub resolved: ResolvedCommand,
+    pub pid: Option<u32>,
+    pub started_at: u64,
+    pub state: ProcessState,
+    pub restart_count: u32,
+    pub options: ProcessOptions,
+    // Recent stdout/stderr lines, shared with the reader tasks
+    pub log: ProcessLog,
+    // Native MCP client requests waiting for their response on stdout
+    pub pending: PendingRequests,
+    // Handshake done by the native client for the current run
+    pub session: McpSession,
+    // Channel of the frontend that started or last attached to the process
+    pub events: ProcessEvents,
+    // Recording and inspector capture of the process's messages
+    pub tap: TrafficTap,
+    pub shutdown_grace_period: Duration,
+    // Fired by stop_external_process with the grace period to use; the monitor task
+    // owns the child and runs the staged shutdown
+    stop_signal: Option<oneshot::Sender<Duration>>,
+    stop_receiver: Option<oneshot::Receiver<Duration>>,
 }
 
 impl ManagedProcess {
-    pub fn new(child: Child) -> Self {
+    pub fn new(child: Child, events: ProcessEvents) -> Self {
+        let (stop_signal, stop_receiver) = oneshot::channel();
         Self {
+            pid: child.id(),
             child: Some(child),
-            stdin: None,
+            writer: None,
+            command: String::new(),
+            args: Vec::new(),
+            resolved: ResolvedCommand::default(),
+            started_at: now_millis(),
+            state: ProcessState::Running,
+            restart_count: 0,
+            options: ProcessOptions::default(),
+            log: ProcessLog::default(),
+            pending: PendingRequests::default(),
+            session: McpSession::default(),
+            events,
+            tap: TrafficTap::default(),
+            shutdown_grace_period: DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            stop_signal: Some(stop_signal),
+            stop_receiver: Some(stop_receiver),
         }
     }
 
-    pub fn with_stdin(mut self, stdin: ChildStdin) -> Self {
-        self.stdin = Some(stdin);
+    pub fn with_writer(mut self, writer: StdinWriter) -> Self {
+        self.writer = Some(writer);
+        self
+    }
+
+    pub fn with_command(mut self, command: String, args: Vec<String>) -> Self {
+        self.command = command;
+        self.args = args;
+        self
+    }
+
+    pub fn with_resolved(mut self, resolved: ResolvedCommand) -> Self {
+        self.resolved = resolved;
+        self
+    }
+
+    pub fn with_log(mut self, log: ProcessLog) -> Self {
+        self.log = log;
+        self
+    }
+
+    pub fn with_pending(mut self, pending: PendingRequests) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    pub fn with_tap(mut self, tap: TrafficTap) -> Self {
+        self.tap = tap;
+        self
+    }
+
+    pub fn with_options(mut self, options: ProcessOptions) -> Self {
+        self.shutdown_grace_period = options.shutdown_grace_period();
+        self.options = options;
         self
     }
 
@@ -30,43 +269,92 @@ impl ManagedProcess {
         self.child.take()
     }
 
-    pub fn into_child(self) -> Option<Child> {
-        self.child
+    /// Marks the process as exiting, closes its stdin and asks the monitor task to
+    /// shut it down, using `grace_period` instead of the configured one if given.
+    /// Returns `false` if a stop was already requested.
+    pub fn request_stop(&mut self, grace_period: Option<Duration>) -> bool {
+        self.state = ProcessState::Exiting;
+        self.writer = None;
+        match self.stop_signal.take() {
+            Some(signal) => {
+                // The receiver may already be gone if the process just exited
+                let _ = signal.send(grace_period.unwrap_or(self.shutdown_grace_period));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn info(&self, id: &str) -> ProcessInfo {
+        ProcessInfo {
+            id: id.to_string(),
+            command: self.command.clone(),
+            args: self.args.clone(),
+            resolved_path: self.resolved.program.to_string_lossy().into_owned(),
+            pid: self.pid,
+            started_at: self.started_at,
+            state: self.state,
+            restart_count: self.restart_count,
+            ignored_stdout_lines: self.log.ignored_stdout_lines(),
+            recording_path: self
+                .tap
+                .recorder()
+                .map(|recorder| recorder.path().to_string_lossy().into_owned()),
+        }
     }
 }
 
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
 // Use a Mutex to safely share the process map across threads
-#[derive(Default)]
-pub struct ProcessRegistry(Arc<Mutex<HashMap<String, ManagedProcess>>>);
+#[derive(Default, Clone)]
+pub struct ProcessRegistry {
+    processes: Arc<Mutex<HashMap<String, ManagedProcess>>>,
+    // Logs of processes that already left the map, so diagnostics survive the exit
+    exited_logs: Arc<Mutex<HashMap<String, ExitedLog>>>,
+    // Captures the traffic of every process for the inspector
+    inspector: TrafficInspector,
+}
 
 impl Deref for ProcessRegistry {
     type Target = Mutex<HashMap<String, ManagedProcess>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl Clone for ProcessRegistry {
-    fn clone(&self) -> Self {
-        ProcessRegistry(self.0.clone())
+        &self.processes
     }
 }
 
 impl DerefMut for ProcessRegistry {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        Arc::get_mut(&mut self.0).expect("Cannot get mutable reference when multiple owners exist")
+        Arc::get_mut(&mut self.processes)
+            .expect("Cannot get mutable reference when multiple owners exist")
     }
 }
 
 impl ProcessRegistry {
+    /// Captures into `inspector`, shared with the remote connections, instead of
+    /// an inspector of its own.
+    pub fn with_inspector(mut self, inspector: TrafficInspector) -> Self {
+        self.inspector = inspector;
+        self
+    }
+
+    pub fn inspector(&self) -> &TrafficInspector {
+        &self.inspector
+    }
+
     pub fn lock(
         &self,
     ) -> Result<
         MutexGuard<HashMap<String, ManagedProcess>>,
         std::sync::PoisonError<MutexGuard<HashMap<String, ManagedProcess>>>,
     > {
-        self.0.lock()
+        self.processes.lock()
     }
 
     pub fn access<F, R>(
@@ -76,10 +364,34 @@ impl ProcessRegistry {
     where
         F: FnOnce(&mut HashMap<String, ManagedProcess>) -> R,
     {
-        let mut guard = self.0.lock()?;
+        let mut guard = self.processes.lock()?;
         Ok(f(&mut guard))
     }
 
+    /// Keeps the log of a process that left the registry for `EXITED_LOG_RETENTION`.
+    pub fn retain_log(&self, process_id: &str, log: ProcessLog) {
+        if let Ok(mut exited_logs) = self.exited_logs.lock() {
+            exited_logs.retain(|_, exited| exited.exited_at.elapsed() < EXITED_LOG_RETENTION);
+            exited_logs.insert(
+                process_id.to_string(),
+                ExitedLog {
+                    log,
+                    exited_at: tokio::time::Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Log of a running process, or of one that exited within the retention period.
+    pub fn process_log(&self, process_id: &str) -> Option<ProcessLog> {
+        if let Some(managed_process) = self.processes.lock().ok()?.get(process_id) {
+            return Some(managed_process.log.clone());
+        }
+        let mut exited_logs = self.exited_logs.lock().ok()?;
+        exited_logs.retain(|_, exited| exited.exited_at.elapsed() < EXITED_LOG_RETENTION);
+        exited_logs.get(process_id).map(|exited| exited.log.clone())
+    }
+
     // pub fn clone_inner(&self) -> Result<HashMap<String, ManagedProcess>, std::sync::PoisonError<MutexGuard<HashMap<String, ManagedProcess>>>> {
     //     let guard = self.0.lock()?;
     //     Ok(guard.clone())
@@ -101,111 +413,607 @@ pub fn emit_event<S: Clone + serde::Serialize>(
     }
 }
 
+/// Bytes requested from stdout per read.
+const STDOUT_READ_CHUNK: usize = 8 * 1024;
+
 // Function to handle reading stdout from the process
 pub async fn handle_stdout(
-    mut stdout: BufReader<ChildStdout>,
+    mut stdout: ChildStdout,
+    mut decoder: MessageDecoder,
     process_id: String,
-    app_handle: AppHandle,
+    log: ProcessLog,
+    pending: PendingRequests,
+    events: ProcessEvents,
+    tap: TrafficTap,
 ) {
-    let mut line_buf = String::new();
+    let mut chunk = vec![0u8; STDOUT_READ_CHUNK];
     loop {
-       
-        match stdout.read_line(&mut line_buf).await {
+        let decoded = match stdout.read(&mut chunk).await {
             Ok(0) => {
                 // EOF reached
                 println!("Process {} stdout closed.", process_id);
+                let decoded = decoder.finish();
+                deliver_stdout(decoded, &process_id, &log, &pending, &events, &tap);
                 break;
             }
-            Ok(_) => {
-                // Attempt to parse the line as JSON
-                let trimmed_line = line_buf.trim();
-                if !trimmed_line.is_empty() {
-                    // Emit the raw line or parsed JSON
-                    // For robustness, you might want error handling for JSON parsing here
-                    println!(
-                        "Got stdout line from process {}: {}",
-                        process_id, trimmed_line
-                    );
-                    emit_event(
-                        &format!("process_message_{}", process_id),
-                        trimmed_line.to_string(), // Send as string, frontend can parse JSON
-                        &app_handle,
-                    );
-                }
-                line_buf.clear(); // Clear buffer for the next line
-            }
+            Ok(n) => decoder.feed(&chunk[..n]),
             Err(e) => {
                 eprintln!("Error reading stdout for process {}: {}", process_id, e);
-                emit_event(
-                    &format!("process_error_{}", process_id),
-                    format!("Error reading stdout: {}", e),
-                    &app_handle,
-                );
+                log.push(LogStream::System, format!("Error reading stdout: {}", e));
+                events.send(ProcessEvent::Error(format!("Error reading stdout: {}", e)));
                 break;
             }
+        };
+        deliver_stdout(decoded, &process_id, &log, &pending, &events, &tap);
+    }
+}
+
+// Logs decoded stdout messages and hands them to the native client or the frontend.
+// Framing errors only cost the affected message, so the stream keeps going.
+fn deliver_stdout(
+    decoded: Vec<Decoded>,
+    process_id: &str,
+    log: &ProcessLog,
+    pending: &PendingRequests,
+    events: &ProcessEvents,
+    tap: &TrafficTap,
+) {
+    for item in decoded {
+        match item {
+            Decoded::Message(message) => {
+                let parsed = match parse_jsonrpc(&message) {
+                    Ok(parsed) => parsed,
+                    Err(reason) => {
+                        // Servers printing banners or logs to stdout keep working
+                        let count = log.push_ignored_stdout(message.as_str());
+                        eprintln!(
+                            "Ignored stdout line #{} from process {} ({}): {}",
+                            count, process_id, reason, message
+                        );
+                        continue;
+                    }
+                };
+                println!("Got stdout message from process {}: {}", process_id, message);
+                log.push(LogStream::Stdout, message.as_str());
+                tap.inbound(&message);
+                // Responses to the native client are not meant for the frontend
+                if pending.try_resolve(&parsed) {
+                    continue;
+                }
+                // Sent as a string, the frontend parses the JSON
+                events.send(ProcessEvent::Message(message));
+            }
+            Decoded::Error(error) => {
+                eprintln!("Framing error on stdout of process {}: {}", process_id, error);
+                log.push(LogStream::System, format!("Framing error: {}", error));
+                events.send(ProcessEvent::Error(format!(
+                    "Framing error on stdout: {}",
+                    error
+                )));
+            }
         }
     }
-    // Optionally emit a specific event when stdout stream ends
-    // emit_event(&format!("process_stdout_closed_{}", process_id), (), &app_handle);
 }
 
-// Function to handle reading stdout from the process
+// Parses a stdout message, accepting only JSON-RPC 2.0 requests, notifications,
+// responses and batches of them.
+fn parse_jsonrpc(message: &str) -> Result<Value, &'static str> {
+    fn is_jsonrpc(value: &Value) -> bool {
+        value.get("jsonrpc").and_then(Value::as_str) == Some("2.0")
+            && (value.get("method").is_some_and(Value::is_string)
+                || (value.get("id").is_some()
+                    && (value.get("result").is_some() || value.get("error").is_some())))
+    }
+
+    let value: Value = serde_json::from_str(message).map_err(|_| "not JSON")?;
+    let valid = match &value {
+        Value::Array(batch) => !batch.is_empty() && batch.iter().all(is_jsonrpc),
+        single => is_jsonrpc(single),
+    };
+    if valid {
+        Ok(value)
+    } else {
+        Err("not a JSON-RPC 2.0 message")
+    }
+}
+
+// Function to handle reading stderr from the process
 pub async fn handle_stderr(
-    mut stderr: BufReader<ChildStdout>,
+    mut stderr: BufReader<ChildStderr>,
     process_id: String,
-    app_handle: AppHandle,
+    log: ProcessLog,
+    events: ProcessEvents,
 ) {
-    let mut line_buf = String::new();
+    let mut line = String::new();
     loop {
-        match stderr.read_line(&mut line_buf).await {
-            Ok(0) => {
-                // EOF reached
-                println!("Process {} stderr closed.", process_id);
-                break;
-            }
+        match stderr.read_line(&mut line).await {
+            Ok(0) => break, // EOF
             Ok(_) => {
-                // Attempt to parse the line as JSON
-                let trimmed_line = line_buf.trim();
-                if !trimmed_line.is_empty() {
-                    // Emit the raw line or parsed JSON
-                    // For robustness, you might want error handling for JSON parsing here
-                    // emit_event(
-                    //     &format!("process_message_{}", process_id),
-                    //     trimmed_line.to_string(), // Send as string, frontend can parse JSON
-                    //     &app_handle,
-                    // );
-                    println!(
-                        "Got stderr line from process {}: {}",
-                        process_id, trimmed_line
-                    );
-                }
-                line_buf.clear(); // Clear buffer for the next line
+                eprintln!("[Process {} stderr]: {}", process_id, line.trim());
+                log.push(LogStream::Stderr, line.trim());
+                events.send(ProcessEvent::Stderr(line.trim().to_string()));
+                line.clear();
             }
             Err(e) => {
-                eprintln!("Error reading stderr for process {}: {}", process_id, e);
+                eprintln!("Error reading stderr for {}: {}", process_id, e);
                 break;
             }
         }
     }
-    // Optionally emit a specific event when stdout stream ends
-    // emit_event(&format!("process_stdout_closed_{}", process_id), (), &app_handle);
+    println!("Stderr handler task finished for {}.", process_id);
+}
+
+/// Spawns a resolved command with the piped stdio and process settings every
+/// managed process uses.
+pub fn spawn_child(
+    resolved: &ResolvedCommand,
+    options: &ProcessOptions,
+) -> Result<Child, std::io::Error> {
+    let mut cmd = tokio::process::Command::new(&resolved.program);
+    cmd.args(&resolved.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    options.apply_to(&mut cmd);
+    if let Some(path) = &resolved.search_path {
+        cmd.env("PATH", path);
+    }
+    options.limits.apply_to(&mut cmd);
+    if let Some(sandbox) = &options.sandbox {
+        sandbox.apply_to(&mut cmd)?;
+    }
+
+    // Conditional compilation for Windows-specific settings if needed
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+        cmd.creation_flags(CREATE_NO_WINDOW | CREATE_NEW_PROCESS_GROUP);
+    }
+    // Own process group, so stopping the server also stops whatever it spawned
+    #[cfg(unix)]
+    isolate_process_group(&mut cmd);
+
+    cmd.spawn()
+}
+
+/// Takes the child's pipes, starts the stdout/stderr reader tasks and the stdin
+/// writer task, and returns the handle for writing to the process.
+pub fn attach_child_io(
+    child: &mut Child,
+    process_id: &str,
+    options: &ProcessOptions,
+    log: &ProcessLog,
+    pending: &PendingRequests,
+    events: &ProcessEvents,
+    tap: &TrafficTap,
+) -> Result<StdinWriter, std::io::Error> {
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| std::io::Error::other("Failed to capture stdin"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| std::io::Error::other("Failed to capture stdout"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| std::io::Error::other("Failed to capture stderr"))?;
+
+    // Stdout and stdin share the framing, so auto-detection also applies to writes
+    let framing = Framing::new(options.framing);
+    let decoder = MessageDecoder::new(
+        options.framing,
+        options.max_message_size(),
+        framing.clone(),
+    );
+
+    // Spawn task to read stdout
+    let stdout_events = events.clone();
+    let stdout_pid = process_id.to_string();
+    let stdout_log = log.clone();
+    let stdout_pending = pending.clone();
+    let stdout_tap = tap.clone();
+    tokio::spawn(async move {
+        handle_stdout(
+            stdout,
+            decoder,
+            stdout_pid,
+            stdout_log,
+            stdout_pending,
+            stdout_events,
+            stdout_tap,
+        )
+        .await;
+    });
+    println!("Spawned stdout handler task for process {}.", process_id);
+
+    // Spawn task to read stderr
+    let stderr_events = events.clone();
+    let stderr_pid = process_id.to_string();
+    let stderr_log = log.clone();
+    tokio::spawn(async move {
+        handle_stderr(BufReader::new(stderr), stderr_pid, stderr_log, stderr_events).await;
+    });
+    println!("Spawned stderr handler task for process {}.", process_id);
+
+    Ok(StdinWriter::spawn(
+        stdin,
+        process_id.to_string(),
+        options.write_timeout(),
+        framing,
+        tap.clone(),
+    ))
+}
+
+/// Makes the spawned process the leader of a new process group, so its whole tree
+/// (`npx` -> node, `sh -c` -> server, ...) can be signalled at once.
+#[cfg(unix)]
+pub fn isolate_process_group(cmd: &mut tokio::process::Command) {
+    cmd.process_group(0);
+}
+
+/// Sends `signal` to every process in the group led by `pgid`.
+#[cfg(unix)]
+pub fn signal_process_group(pgid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // SAFETY: killpg(2) has no memory-safety preconditions
+    if unsafe { libc::killpg(pgid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Forcefully kills the process tree whose root has the given OS pid.
+pub fn kill_process_tree_by_pid(pid: u32) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        signal_process_group(pid, libc::SIGKILL)
+    }
+    #[cfg(windows)]
+    {
+        // No process groups to signal on Windows; let taskkill walk the tree
+        let status = std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(std::io::Error::other(format!("taskkill exited with {}", status)))
+        }
+    }
+}
+
+/// Forcefully kills the process tree rooted at `child`.
+fn kill_process_tree(child: &mut Child) -> std::io::Result<()> {
+    if let Some(pid) = child.id() {
+        if kill_process_tree_by_pid(pid).is_ok() {
+            return Ok(());
+        }
+    }
+    child.start_kill()
+}
+
+// Staged shutdown of the whole process group: stdin is already closed by
+// request_stop, so give the process `grace_period` to exit on its own, then
+// SIGTERM, then SIGKILL.
+async fn shutdown_child(
+    child: &mut Child,
+    process_id: &str,
+    grace_period: Duration,
+    events: &ProcessEvents,
+) -> Result<std::process::ExitStatus, std::io::Error> {
+    println!(
+        "Stop requested for process {}, waiting {:?} after closing stdin.",
+        process_id, grace_period
+    );
+    events.send(ProcessEvent::Shutdown {
+        stage: ShutdownStage::StdinClosed,
+    });
+    if let Ok(result) = tokio::time::timeout(grace_period, child.wait()).await {
+        return result;
+    }
+
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        println!("Process {} still running, sending SIGTERM.", process_id);
+        events.send(ProcessEvent::Shutdown {
+            stage: ShutdownStage::Terminate,
+        });
+        match signal_process_group(pid, libc::SIGTERM) {
+            Ok(()) => {
+                if let Ok(result) = tokio::time::timeout(grace_period, child.wait()).await {
+                    return result;
+                }
+            }
+            Err(e) => eprintln!("Failed to send SIGTERM to process {}: {}", process_id, e),
+        }
+    }
+
+    println!("Process {} still running, killing it.", process_id);
+    events.send(ProcessEvent::Shutdown {
+        stage: ShutdownStage::Kill,
+    });
+    if let Err(e) = kill_process_tree(child) {
+        eprintln!("Failed to send kill signal to process {}: {}", process_id, e);
+    }
+    child.wait().await
+}
+
+/// Overall time budget for stopping every process when Daan quits.
+pub const APP_EXIT_DEADLINE: Duration = Duration::from_secs(5);
+
+/// Gracefully stops every registered process, waiting at most `deadline` overall.
+/// Processes still registered afterwards are force-killed and logged.
+pub async fn shutdown_all(registry: &ProcessRegistry, deadline: Duration) {
+    let started = tokio::time::Instant::now();
+    // The staged shutdown waits twice (after closing stdin and after SIGTERM)
+    let stage_limit = deadline / 2;
+
+    let pending = match registry.lock() {
+        Ok(mut guard) => {
+            for managed_process in guard.values_mut() {
+                let grace_period = managed_process.shutdown_grace_period.min(stage_limit);
+                managed_process.request_stop(Some(grace_period));
+            }
+            guard.len()
+        }
+        Err(poison_error) => {
+            eprintln!("Mutex poisoned when shutting down processes: {}", poison_error);
+            return;
+        }
+    };
+    if pending == 0 {
+        return;
+    }
+    println!("Stopping {} managed process(es) before exit.", pending);
+
+    // Monitor tasks remove entries as their processes exit
+    while started.elapsed() < deadline {
+        match registry.lock() {
+            Ok(guard) if guard.is_empty() => {
+                println!("All managed processes stopped in {:?}.", started.elapsed());
+                return;
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let Ok(guard) = registry.lock() else {
+        eprintln!("Mutex poisoned when collecting processes left after shutdown.");
+        return;
+    };
+    for (id, managed_process) in guard.iter() {
+        eprintln!(
+            "Process {} ({} {:?}, pid {:?}) did not exit within {:?}, killing it.",
+            id, managed_process.command, managed_process.args, managed_process.pid, deadline
+        );
+        if let Some(pid) = managed_process.pid {
+            if let Err(e) = kill_process_tree_by_pid(pid) {
+                eprintln!("Failed to kill process {}: {}", id, e);
+            }
+        }
+    }
+}
+
+// Resolves with the grace period once a stop is requested; never resolves otherwise.
+async fn stop_requested(stop_receiver: &mut Option<oneshot::Receiver<Duration>>) -> Duration {
+    if let Some(receiver) = stop_receiver.as_mut() {
+        if let Ok(grace_period) = receiver.await {
+            return grace_period;
+        }
+        // Sender dropped without a stop request; don't poll the finished receiver again
+        *stop_receiver = None;
+    }
+    std::future::pending().await
+}
+
+// Resolves once a run has lasted `lifetime`; never resolves without a lifetime limit.
+async fn lifetime_expired(lifetime: Option<Duration>) {
+    match lifetime {
+        Some(lifetime) => tokio::time::sleep(lifetime).await,
+        None => std::future::pending().await,
+    }
+}
+
+// Waits for one run of the child, running the staged shutdown if a stop comes in or
+// the lifetime limit is reached first. Returns the wait result, whether the exit was
+// requested and whether the lifetime limit ended the run.
+async fn wait_child(
+    child: &mut Child,
+    stop_receiver: &mut Option<oneshot::Receiver<Duration>>,
+    process_id: &str,
+    options: &ProcessOptions,
+    events: &ProcessEvents,
+) -> (Result<std::process::ExitStatus, std::io::Error>, bool, Option<ResourceLimit>) {
+    // child.id() is gone once the child is reaped, so keep it for the group cleanup
+    #[cfg(unix)]
+    let process_group = child.id();
+
+    let result = tokio::select! {
+        result = child.wait() => (result, false, None),
+        grace_period = stop_requested(stop_receiver) => {
+            (shutdown_child(child, process_id, grace_period, events).await, true, None)
+        }
+        _ = lifetime_expired(options.limits.lifetime()) => {
+            println!("Process {} reached its lifetime limit, shutting it down.", process_id);
+            let grace_period = options.shutdown_grace_period();
+            let result = shutdown_child(child, process_id, grace_period, events).await;
+            (result, false, Some(ResourceLimit::Lifetime))
+        }
+    };
+
+    // The leader is gone, but servers started through npx/uvx/sh may have left
+    // children behind in its group
+    #[cfg(unix)]
+    if let Some(pgid) = process_group {
+        if signal_process_group(pgid, libc::SIGKILL).is_ok() {
+            println!("Killed leftover processes in group of process {}.", process_id);
+        }
+    }
+    result
+}
+
+// Waits out the backoff and starts the process again under the same id, retrying
+// failed spawns. Returns None once retries are exhausted or a stop is requested.
+async fn restart_child(
+    process_id: &str,
+    policy: &RestartPolicy,
+    failures: &mut u32,
+    reason: &str,
+    stop_receiver: &mut Option<oneshot::Receiver<Duration>>,
+    events: &ProcessEvents,
+    registry: &ProcessRegistry,
+) -> Option<Child> {
+    loop {
+        if *failures >= policy.max_retries {
+            eprintln!(
+                "Process {} failed {} times in a row, giving up on restarts.",
+                process_id, failures
+            );
+            return None;
+        }
+        *failures += 1;
+        let delay = policy.backoff(*failures);
+
+        let (resolved, options, log, pending, tap) = {
+            let mut guard = registry.lock().ok()?;
+            let managed_process = guard.get_mut(process_id)?;
+            if managed_process.state == ProcessState::Exiting {
+                return None;
+            }
+            managed_process.state = ProcessState::Restarting;
+            managed_process.writer = None;
+            // The new run needs its own handshake; nothing will answer the old requests
+            managed_process.session = McpSession::default();
+            managed_process.pending.clear();
+            (
+                managed_process.resolved.clone(),
+                managed_process.options.clone(),
+                managed_process.log.clone(),
+                managed_process.pending.clone(),
+                managed_process.tap.clone(),
+            )
+        };
+
+        println!(
+            "Restarting process {} in {:?} (attempt {}/{}).",
+            process_id, delay, failures, policy.max_retries
+        );
+        log.push(
+            LogStream::System,
+            format!(
+                "{}; restarting in {:?} (attempt {}/{})",
+                reason, delay, failures, policy.max_retries
+            ),
+        );
+        events.send(ProcessEvent::Restarting(RestartingEvent {
+            attempt: *failures,
+            max_retries: policy.max_retries,
+            delay_ms: delay.as_millis() as u64,
+            reason: reason.to_string(),
+        }));
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = stop_requested(stop_receiver) => {
+                println!("Stop requested for process {} during restart backoff.", process_id);
+                return None;
+            }
+        }
+
+        let mut child = match spawn_child(&resolved, &options) {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("Failed to restart process {}: {}", process_id, e);
+                log.push(LogStream::System, format!("Failed to restart process: {}", e));
+                events.send(ProcessEvent::Error(format!(
+                    "Failed to restart process: {}",
+                    e
+                )));
+                continue;
+            }
+        };
+        let writer = match attach_child_io(
+            &mut child,
+            process_id,
+            &options,
+            &log,
+            &pending,
+            events,
+            &tap,
+        ) {
+            Ok(writer) => writer,
+            Err(e) => {
+                eprintln!("Failed to attach to restarted process {}: {}", process_id, e);
+                let _ = kill_process_tree(&mut child);
+                continue;
+            }
+        };
+
+        let restart_count = {
+            let mut guard = registry.lock().ok()?;
+            let managed_process = guard.get_mut(process_id)?;
+            managed_process.pid = child.id();
+            managed_process.started_at = now_millis();
+            managed_process.restart_count += 1;
+            // A stop that raced with the respawn has already been signalled; the
+            // monitor picks it up on the next wait, so keep the entry exiting
+            if managed_process.state != ProcessState::Exiting {
+                managed_process.state = ProcessState::Running;
+                managed_process.writer = Some(writer);
+            }
+            managed_process.restart_count
+        };
+        println!("Process {} restarted ({} restarts so far).", process_id, restart_count);
+        events.send(ProcessEvent::Restarted { restart_count });
+        return Some(child);
+    }
+}
+
+/// Stderr lines sent along with the exit of a process.
+pub const EXIT_STDERR_LINES: usize = 20;
+
+/// Time the stderr reader gets to pick up what the process wrote right before
+/// it exited, so the exit info includes the actual error.
+const STDERR_DRAIN_DELAY: Duration = Duration::from_millis(100);
+
+fn exit_status_info(status: &std::process::ExitStatus) -> ExitInfo {
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(status);
+    #[cfg(not(unix))]
+    let signal = None;
+    ExitInfo {
+        code: status.code(),
+        signal,
+        ..ExitInfo::default()
+    }
 }
 
 // Function to monitor process completion
 pub async fn monitor_process(
     process_id: String,
-    app_handle: AppHandle,
+    events: ProcessEvents,
     registry: ProcessRegistry, // Takes the Arc<Mutex<...>> wrapper
 ) {
     // --- Step 1: Take the Child handle out of the registry ---
-    let child_to_monitor: Option<Child> = { // Scope for the first lock guard
+    let child_to_monitor: Option<(Child, Option<oneshot::Receiver<Duration>>)> = { // Scope for the first lock guard
         let lock_result = registry.lock();
         match lock_result {
             Ok(mut guard) => {
                 // Get mutable access to the ManagedProcess entry
                 if let Some(managed_proc) = guard.get_mut(&process_id) {
-                    // Take the child handle out of the ManagedProcess struct
-                    managed_proc.take_child() // Returns Option<Child>
+                    // Take the child handle (and the stop signal) out of the ManagedProcess struct
+                    let stop_receiver = managed_proc.stop_receiver.take();
+                    managed_proc
+                        .take_child()
+                        .map(|child| (child, stop_receiver))
                 } else {
                     // Process entry somehow already gone? Log error.
                     eprintln!("Monitor task could not find process {} in registry to take child.", process_id);
@@ -220,12 +1028,60 @@ pub async fn monitor_process(
         // First MutexGuard is dropped here
     }; // --- First lock guard scope ends ---
 
-    // --- Step 2: Wait for the process completion (if child was obtained) ---
-    let mut maybe_wait_result: Option<Result<std::process::ExitStatus, std::io::Error>> = None;
-    if let Some(mut child) = child_to_monitor {
-        println!("Monitoring process {} for completion.", process_id); // Log now happens *before* waiting
-        maybe_wait_result = Some(child.wait().await); // Await happens *outside* the lock
-                                                      // child handle is dropped here
+    // --- Step 2: Wait for the process completion (if child was obtained), restarting it per policy ---
+    let mut maybe_wait_result: Option<(Result<std::process::ExitStatus, std::io::Error>, bool)> =
+        None;
+    // Limit that ended the last run, if the monitor itself enforced it
+    let mut limit_hit = None;
+    let options = registry
+        .lock()
+        .ok()
+        .and_then(|guard| guard.get(&process_id).map(|p| p.options.clone()))
+        .unwrap_or_default();
+    if let Some((mut child, mut stop_receiver)) = child_to_monitor {
+        let restart_policy = &options.restart;
+        let mut failures: u32 = 0;
+
+        loop {
+            println!("Monitoring process {} for completion.", process_id); // Log now happens *before* waiting
+            let run_started = tokio::time::Instant::now();
+            // Await happens *outside* the lock
+            let (wait_result, stopped, limit) =
+                wait_child(&mut child, &mut stop_receiver, &process_id, &options, &events).await;
+            limit_hit = limit;
+
+            if stopped || !restart_policy.should_restart(&wait_result) {
+                maybe_wait_result = Some((wait_result, stopped));
+                break;
+            }
+            if run_started.elapsed() >= RESTART_RESET_AFTER {
+                failures = 0;
+            }
+            let reason = match (&wait_result, limit) {
+                (_, Some(limit)) => format!("{} reached", limit),
+                (Ok(status), None) => format!("Exited with status: {}", status),
+                (Err(e), None) => format!("Error waiting for process: {}", e),
+            };
+            match restart_child(
+                &process_id,
+                restart_policy,
+                &mut failures,
+                &reason,
+                &mut stop_receiver,
+                &events,
+                &registry,
+            )
+            .await
+            {
+                Some(new_child) => child = new_child,
+                None => {
+                    // Gave up, or a stop came in during the backoff (see Step 3)
+                    maybe_wait_result = Some((wait_result, false));
+                    break;
+                }
+            }
+        }
+        // child handle is dropped here
     } else {
         eprintln!(
             "Monitor task for process {} could not obtain child handle.",
@@ -235,28 +1091,56 @@ pub async fn monitor_process(
     }
 
     // --- Step 3: Emit events based on wait result ---
-    if let Some(wait_result) = maybe_wait_result {
+    let log = registry.process_log(&process_id);
+    let (started_at, exiting) = registry
+        .lock()
+        .ok()
+        .and_then(|guard| {
+            guard
+                .get(&process_id)
+                .map(|p| (Some(p.started_at), p.state == ProcessState::Exiting))
+        })
+        .unwrap_or_default();
+    let mut exit_info = ExitInfo::default();
+    if let Some((wait_result, stopped)) = maybe_wait_result {
         match wait_result {
             Ok(status) => {
                 println!("Process {} exited with status: {}", process_id, status);
-                emit_event(
-                    &format!("process_closed_{}", process_id),
-                    format!("Exited with status: {}", status),
-                    &app_handle,
-                );
+                if let Some(log) = &log {
+                    log.push(LogStream::System, format!("Exited with status: {}", status));
+                }
+                exit_info = exit_status_info(&status);
             }
             Err(e) => {
                 eprintln!("Error waiting for process {}: {}", process_id, e);
-                emit_event(
-                    &format!("process_error_{}", process_id),
-                    format!("Error waiting for process: {}", e),
-                    &app_handle,
-                );
+                if let Some(log) = &log {
+                    log.push(LogStream::System, format!("Error waiting for process: {}", e));
+                }
+                events.send(ProcessEvent::Error(format!(
+                    "Error waiting for process: {}",
+                    e
+                )));
             }
         }
+        exit_info.stop_requested = stopped || exiting;
+        if !exit_info.stop_requested {
+            tokio::time::sleep(STDERR_DRAIN_DELAY).await;
+        }
+    }
+    // Without a wait result we couldn't get the child, potentially stopped
+    // externally; the frontend is still told the process is gone
+    exit_info.uptime_ms =
+        started_at.map_or(0, |started_at| now_millis().saturating_sub(started_at));
+    if let Some(log) = &log {
+        exit_info.stderr_tail = log.tail_of(LogStream::Stderr, EXIT_STDERR_LINES);
+    }
+    if !exit_info.stop_requested {
+        exit_info.limit_exceeded = limit_hit.or_else(|| options.limits.detect(&exit_info));
     }
-    // If maybe_wait_result is None, it means we couldn't get the child, potentially stopped externally.
-    // A closed event might have been emitted by stop_external_process or similar.
+    if let (Some(limit), Some(log)) = (exit_info.limit_exceeded, &log) {
+        log.push(LogStream::System, format!("{} exceeded", limit));
+    }
+    events.send(ProcessEvent::Closed(exit_info));
 
     // --- Step 4: Re-acquire lock and remove the ManagedProcess entry ---
     {
@@ -265,7 +1149,10 @@ pub async fn monitor_process(
         match lock_result {
             Ok(mut guard) => {
                 // Remove the entry regardless of wait result, as monitoring is finished.
-                if guard.remove(&process_id).is_some() {
+                if let Some(managed_process) = guard.remove(&process_id) {
+                    // The stdout task may still hold the pending map; fail the waiters now
+                    managed_process.pending.clear();
+                    registry.retain_log(&process_id, managed_process.log);
                     println!(
                         "Removed process {} from registry after monitoring.",
                         process_id
@@ -290,3 +1177,75 @@ pub async fn monitor_process(
 
     println!("Finished monitoring task for process {}.", process_id);
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::io::AsyncBufReadExt;
+
+    // A pid is gone once kill(pid, 0) fails, or it only lingers as a zombie
+    // (containers without a reaping init keep orphans around as zombies)
+    fn is_alive(pid: i32) -> bool {
+        // SAFETY: signal 0 only checks for existence
+        if unsafe { libc::kill(pid, 0) } != 0 {
+            return false;
+        }
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat
+                .rsplit_once(')')
+                .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[test]
+    fn only_jsonrpc_messages_pass_stdout_validation() {
+        for message in [
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#,
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"x"}}"#,
+            r#"[{"jsonrpc":"2.0","id":"a","method":"ping"}]"#,
+        ] {
+            assert!(parse_jsonrpc(message).is_ok(), "{}", message);
+        }
+        for message in [
+            "Server listening on stdio",
+            r#"{"level":"info","msg":"ready"}"#,
+            r#"{"jsonrpc":"1.0","id":1,"result":{}}"#,
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            "[]",
+        ] {
+            assert!(parse_jsonrpc(message).is_err(), "{}", message);
+        }
+    }
+
+    #[tokio::test]
+    async fn killing_process_group_kills_grandchildren() {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        isolate_process_group(&mut cmd);
+        let mut child = cmd.spawn().expect("failed to spawn sh");
+
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        stdout.read_line(&mut line).await.unwrap();
+        let grandchild: i32 = line.trim().parse().expect("grandchild pid");
+        assert!(is_alive(grandchild));
+
+        kill_process_tree(&mut child).unwrap();
+        child.wait().await.unwrap();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while is_alive(grandchild) {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "grandchild {} survived the group kill",
+                grandchild
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::mcp::control::ShutdownStage;
+use crate::mcp::limits::ResourceLimit;
+use crate::mcp::supervisor::RestartingEvent;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Channel;
+use tokio::sync::mpsc;
+
+/// Everything a process reports to the frontend, in the order it happened.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum ProcessEvent {
+    /// A JSON-RPC message read from stdout.
+    Message(String),
+    Stderr(String),
+    Error(String),
+    Restarting(RestartingEvent),
+    Restarted { restart_count: u32 },
+    Shutdown { stage: ShutdownStage },
+    /// Sent once, after the process exited for good.
+    Closed(ExitInfo),
+}
+
+/// How a process (or remote connection) ended, so the frontend can tell a
+/// requested stop from a crash.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitInfo {
+    /// Exit code; `None` if the process was killed by a signal or could not be
+    /// waited for.
+    pub code: Option<i32>,
+    /// Signal that terminated the process (Unix only).
+    pub signal: Option<i32>,
+    /// Whether the exit followed `stop_external_process` or Daan quitting.
+    pub stop_requested: bool,
+    /// Milliseconds the last run was up.
+    pub uptime_ms: u64,
+    /// Last lines written to stderr, oldest first.
+    pub stderr_tail: Vec<String>,
+    /// Resource limit the process ran into, as far as it can be told.
+    pub limit_exceeded: Option<ResourceLimit>,
+    /// Why a remote connection closed; `None` for processes.
+    pub reason: Option<String>,
+}
+
+/// Where events from the backend are delivered. The app hands in the frontend's
+/// `Channel`; anything else (tests, a headless host) can implement this or pass
+/// an mpsc sender.
+pub trait EventSink<E = ProcessEvent>: Send + Sync {
+    fn send(&self, event: E) -> Result<(), String>;
+}
+
+impl<E: Serialize + Send + Sync> EventSink<E> for Channel<E> {
+    fn send(&self, event: E) -> Result<(), String> {
+        Channel::send(self, event).map_err(|e| e.to_string())
+    }
+}
+
+impl<E: Send> EventSink<E> for mpsc::UnboundedSender<E> {
+    fn send(&self, event: E) -> Result<(), String> {
+        mpsc::UnboundedSender::send(self, event).map_err(|_| "Receiver was dropped".to_string())
+    }
+}
+
+/// Sink a process's events go to: the one passed by whoever started it, or that
+/// last attached to it. Shared by the process's reader and monitor tasks, and
+/// delivered only to that caller, in order, from spawn time on.
+#[derive(Clone)]
+pub struct ProcessEvents(Arc<Mutex<Box<dyn EventSink>>>);
+
+impl ProcessEvents {
+    pub fn new(sink: impl EventSink + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(sink))))
+    }
+
+    /// Sends later events to `sink` instead, e.g. after the webview reloaded.
+    pub fn replace(&self, sink: impl EventSink + 'static) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = Box::new(sink);
+        }
+    }
+
+    pub fn send(&self, event: ProcessEvent) {
+        let Ok(sink) = self.0.lock() else {
+            return;
+        };
+        if let Err(e) = sink.send(event) {
+            eprintln!("Failed to send process event: {}", e);
+        }
+    }
+}
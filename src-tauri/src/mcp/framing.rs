@@ -0,0 +1,389 @@
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Default limit for a single message read from a process's stdout.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Limit for the header block of a `Content-Length` framed message.
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+const CONTENT_LENGTH: &[u8] = b"content-length";
+
+/// How JSON-RPC messages are delimited on a process's stdin/stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FramingMode {
+    /// One message per line, as the MCP stdio transport specifies. Pretty-printed
+    /// JSON spanning several lines is still read as one message.
+    #[default]
+    Ndjson,
+    /// LSP-style `Content-Length: <n>\r\n\r\n<body>` framing.
+    ContentLength,
+    /// Detects the framing of each message read. Writes newline-delimited messages
+    /// until the process has used `Content-Length` framing itself.
+    Auto,
+}
+
+/// Framing used for writing to a process, shared between its stdout reader (which
+/// may detect it) and its stdin writer.
+#[derive(Debug, Clone, Default)]
+pub struct Framing {
+    content_length: Arc<AtomicBool>,
+}
+
+impl Framing {
+    pub fn new(mode: FramingMode) -> Self {
+        Self {
+            content_length: Arc::new(AtomicBool::new(mode == FramingMode::ContentLength)),
+        }
+    }
+
+    /// Frames `message` for writing to stdin.
+    pub fn encode(&self, mut message: String) -> String {
+        if self.content_length.load(Ordering::Relaxed) {
+            format!("Content-Length: {}\r\n\r\n{}", message.len(), message)
+        } else {
+            message.push('\n');
+            message
+        }
+    }
+
+    fn detected(&self, content_length: bool) {
+        self.content_length.store(content_length, Ordering::Relaxed);
+    }
+}
+
+/// Result of decoding stdout; errors only cost the message they occurred in.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decoded {
+    Message(String),
+    Error(String),
+}
+
+// What to drop before decoding resumes after an error
+enum Discard {
+    Nothing,
+    Bytes(usize),
+    Line,
+}
+
+/// Splits a process's stdout into messages, working on bytes so that a chunk
+/// boundary or invalid UTF-8 never breaks the stream.
+pub struct MessageDecoder {
+    mode: FramingMode,
+    max_message_size: usize,
+    framing: Framing,
+    buffer: Vec<u8>,
+    // Lines of a multi-line JSON value read so far
+    partial: Vec<u8>,
+    discard: Discard,
+}
+
+impl MessageDecoder {
+    pub fn new(mode: FramingMode, max_message_size: usize, framing: Framing) -> Self {
+        Self {
+            mode,
+            max_message_size,
+            framing,
+            buffer: Vec::new(),
+            partial: Vec::new(),
+            discard: Discard::Nothing,
+        }
+    }
+
+    /// Adds bytes read from stdout and returns the messages completed by them.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Decoded> {
+        self.buffer.extend_from_slice(bytes);
+        let mut decoded = Vec::new();
+        while let Some(item) = self.next() {
+            if let Some(item) = item {
+                decoded.push(item);
+            }
+        }
+        decoded
+    }
+
+    /// Called at EOF: a last line without a trailing newline is still a message,
+    /// anything else left over is reported as truncated.
+    pub fn finish(&mut self) -> Vec<Decoded> {
+        let mut decoded = if self.at_header() {
+            Vec::new()
+        } else {
+            self.feed(b"\n")
+        };
+        if !self.partial.is_empty()
+            || !matches!(self.discard, Discard::Nothing)
+            || self.buffer.iter().any(|b| !b.is_ascii_whitespace())
+        {
+            decoded.push(Decoded::Error(
+                "Stream ended in the middle of a message".to_string(),
+            ));
+        }
+        self.buffer.clear();
+        self.partial.clear();
+        self.discard = Discard::Nothing;
+        decoded
+    }
+
+    // Consumes at most one frame. `None` means more input is needed; `Some(None)`
+    // means bytes were consumed without producing anything.
+    fn next(&mut self) -> Option<Option<Decoded>> {
+        match self.discard {
+            Discard::Nothing => {}
+            Discard::Bytes(remaining) => {
+                let dropped = remaining.min(self.buffer.len());
+                self.buffer.drain(..dropped);
+                if dropped < remaining {
+                    self.discard = Discard::Bytes(remaining - dropped);
+                    return None;
+                }
+                self.discard = Discard::Nothing;
+            }
+            Discard::Line => {
+                let end = self.buffer.iter().position(|b| *b == b'\n');
+                let Some(end) = end else {
+                    self.buffer.clear();
+                    return None;
+                };
+                self.buffer.drain(..=end);
+                self.discard = Discard::Nothing;
+            }
+        }
+
+        if self.partial.is_empty() && self.mode != FramingMode::Ndjson {
+            // Whitespace between frames carries nothing
+            let start = self
+                .buffer
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(self.buffer.len());
+            self.buffer.drain(..start);
+            if self.buffer.is_empty() {
+                return None;
+            }
+            if self.mode == FramingMode::ContentLength {
+                return self.next_with_header();
+            }
+            let prefix = self.buffer.len().min(CONTENT_LENGTH.len());
+            if !self.buffer[..prefix].eq_ignore_ascii_case(&CONTENT_LENGTH[..prefix]) {
+                return self.next_line();
+            }
+            if prefix < CONTENT_LENGTH.len() {
+                // Could still turn out to be a header
+                return None;
+            }
+            return self.next_with_header();
+        }
+        self.next_line()
+    }
+
+    fn next_line(&mut self) -> Option<Option<Decoded>> {
+        let Some(end) = self.buffer.iter().position(|b| *b == b'\n') else {
+            if self.partial.len() + self.buffer.len() > self.max_message_size {
+                self.partial.clear();
+                self.buffer.clear();
+                self.discard = Discard::Line;
+                return Some(Some(self.too_large()));
+            }
+            return None;
+        };
+        let line: Vec<u8> = self.buffer.drain(..=end).collect();
+        let mut candidate = std::mem::take(&mut self.partial);
+        candidate.extend_from_slice(&line);
+
+        let text = candidate.trim_ascii();
+        if text.is_empty() {
+            return Some(None);
+        }
+        if text.starts_with(b"{") || text.starts_with(b"[") {
+            if let Err(e) = serde_json::from_slice::<serde::de::IgnoredAny>(text) {
+                if e.is_eof() {
+                    // A pretty-printed value continues on the next line
+                    if candidate.len() > self.max_message_size {
+                        return Some(Some(self.too_large()));
+                    }
+                    self.partial = candidate;
+                    return Some(None);
+                }
+            }
+            // Stray log lines say nothing about the framing the process expects
+            if self.mode == FramingMode::Auto {
+                self.framing.detected(false);
+            }
+        }
+        Some(Some(to_message(text)))
+    }
+
+    fn next_with_header(&mut self) -> Option<Option<Decoded>> {
+        let Some((header_end, body_start)) = find_header_end(&self.buffer) else {
+            if self.buffer.len() > MAX_HEADER_SIZE {
+                self.buffer.clear();
+                self.discard = Discard::Line;
+                return Some(Some(Decoded::Error(format!(
+                    "Dropped a header block longer than {} bytes",
+                    MAX_HEADER_SIZE
+                ))));
+            }
+            return None;
+        };
+
+        let length = String::from_utf8_lossy(&self.buffer[..header_end])
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+        let Some(length) = length else {
+            self.buffer.drain(..body_start);
+            return Some(Some(Decoded::Error(
+                "Dropped a message without a valid Content-Length header".to_string(),
+            )));
+        };
+        if length > self.max_message_size {
+            self.buffer.drain(..body_start);
+            self.discard = Discard::Bytes(length);
+            return Some(Some(self.too_large()));
+        }
+        if self.buffer.len() < body_start + length {
+            return None;
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..body_start + length).collect();
+        if self.mode == FramingMode::Auto {
+            self.framing.detected(true);
+        }
+        let body = frame[body_start..].trim_ascii();
+        if body.is_empty() {
+            return Some(None);
+        }
+        Some(Some(to_message(body)))
+    }
+
+    // Whether the buffered bytes begin a Content-Length frame
+    fn at_header(&self) -> bool {
+        match self.mode {
+            FramingMode::Ndjson => false,
+            FramingMode::ContentLength => true,
+            FramingMode::Auto => {
+                let text = self.buffer.trim_ascii_start();
+                self.partial.is_empty()
+                    && text.len() >= CONTENT_LENGTH.len()
+                    && text[..CONTENT_LENGTH.len()].eq_ignore_ascii_case(CONTENT_LENGTH)
+            }
+        }
+    }
+
+    fn too_large(&self) -> Decoded {
+        Decoded::Error(format!(
+            "Dropped a message larger than {} bytes",
+            self.max_message_size
+        ))
+    }
+}
+
+// Position of the blank line ending a header block, and where the body starts
+fn find_header_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    buffer.iter().enumerate().find_map(|(i, b)| {
+        if *b != b'\n' {
+            return None;
+        }
+        let rest = &buffer[i + 1..];
+        if rest.starts_with(b"\r\n") {
+            Some((i, i + 3))
+        } else if rest.starts_with(b"\n") {
+            Some((i, i + 2))
+        } else {
+            None
+        }
+    })
+}
+
+fn to_message(bytes: &[u8]) -> Decoded {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Decoded::Message(text.to_string()),
+        Err(e) => Decoded::Error(format!("Dropped a message that is not valid UTF-8: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoder(mode: FramingMode) -> MessageDecoder {
+        MessageDecoder::new(mode, 64, Framing::new(mode))
+    }
+
+    fn message(text: &str) -> Decoded {
+        Decoded::Message(text.to_string())
+    }
+
+    #[test]
+    fn ndjson_joins_chunks_and_pretty_printed_values() {
+        let mut decoder = decoder(FramingMode::Ndjson);
+        assert_eq!(decoder.feed(b"{\"id\":"), vec![]);
+        assert_eq!(
+            decoder.feed(b"1}\r\n\n{\n  \"id\": 2\n"),
+            vec![message("{\"id\":1}")]
+        );
+        assert_eq!(
+            decoder.feed(b"}\nnot json\n"),
+            vec![message("{\n  \"id\": 2\n}"), message("not json"),]
+        );
+        assert_eq!(decoder.feed(b"{\"id\":3}"), vec![]);
+        assert_eq!(decoder.finish(), vec![message("{\"id\":3}")]);
+    }
+
+    #[test]
+    fn ndjson_errors_only_drop_the_broken_message() {
+        let mut decoder = decoder(FramingMode::Ndjson);
+        let decoded = decoder.feed(b"\xff\xfe\n{\"id\":1}\n");
+        assert!(matches!(decoded[0], Decoded::Error(_)));
+        assert_eq!(decoded[1], message("{\"id\":1}"));
+
+        let oversized = vec![b'x'; 100];
+        let decoded = decoder.feed(&oversized);
+        assert!(matches!(decoded[..], [Decoded::Error(_)]));
+        assert_eq!(
+            decoder.feed(b"xxx\n{\"id\":2}\n"),
+            vec![message("{\"id\":2}")]
+        );
+    }
+
+    #[test]
+    fn content_length_frames_and_skips_oversized_bodies() {
+        let mut decoder = decoder(FramingMode::ContentLength);
+        assert_eq!(
+            decoder.feed(b"Content-Length: 8\r\nContent-Type: x\r\n\r\n{\"id\""),
+            vec![]
+        );
+        assert_eq!(decoder.feed(b":1}"), vec![message("{\"id\":1}")]);
+
+        let body = vec![b' '; 100];
+        let mut input = b"Content-Length: 100\r\n\r\n".to_vec();
+        input.extend_from_slice(&body);
+        input.extend_from_slice(b"Content-Length: 8\n\n{\"id\":2}");
+        let decoded = decoder.feed(&input);
+        assert!(matches!(decoded[0], Decoded::Error(_)));
+        assert_eq!(decoded[1], message("{\"id\":2}"));
+
+        let decoded = decoder.feed(b"X-Other: 1\r\n\r\nContent-Length: 2\r\n\r\n{}");
+        assert!(matches!(decoded[0], Decoded::Error(_)));
+        assert_eq!(decoded[1], message("{}"));
+    }
+
+    #[test]
+    fn auto_detects_each_message_and_switches_writes() {
+        let framing = Framing::new(FramingMode::Auto);
+        let mut decoder = MessageDecoder::new(FramingMode::Auto, 64, framing.clone());
+        assert_eq!(framing.encode("{}".to_string()), "{}\n");
+        assert_eq!(
+            decoder.feed(b"{\"id\":1}\ncontent-len"),
+            vec![message("{\"id\":1}")]
+        );
+        assert_eq!(decoder.feed(b"gth: 2\r\n\r\n{}"), vec![message("{}")]);
+        assert_eq!(
+            framing.encode("{}".to_string()),
+            "Content-Length: 2\r\n\r\n{}"
+        );
+    }
+}
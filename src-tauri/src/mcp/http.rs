@@ -0,0 +1,565 @@
+use crate::mcp::remote::{OutgoingMessage, TransportChannels, TransportEvent};
+use crate::mcp::sse::SseParser;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{Response, StatusCode, Url};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Reconnect attempts for an SSE stream before giving up on it.
+const MAX_STREAM_RECONNECTS: u32 = 5;
+
+/// Delay before reconnecting an SSE stream, unless the server sent `retry:`.
+const DEFAULT_STREAM_RETRY: Duration = Duration::from_secs(1);
+
+/// Limit for establishing a connection to a remote server.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Limit for the `DELETE` that ends the session when the connection is closed.
+const SESSION_TERMINATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// URL and headers of a remote server reached over HTTP.
+#[derive(Debug, Clone)]
+pub struct HttpEndpoint {
+    pub url: Url,
+    pub headers: HeaderMap,
+}
+
+impl HttpEndpoint {
+    pub fn new(url: &str, headers: &HashMap<String, String>) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Unsupported URL scheme '{}'.", url.scheme()));
+        }
+        Ok(Self {
+            url,
+            headers: header_map(headers)?,
+        })
+    }
+}
+
+/// Converts user-configured headers, rejecting names or values HTTP can't carry.
+pub fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| format!("Invalid header name: '{}'", name))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| format!("Invalid value for header '{}'", name))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+/// Client for the requests of one remote connection. Has no overall timeout,
+/// since responses may be long-lived event streams.
+pub fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Whether the response body is a `text/event-stream`.
+pub fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+// What a finished request task asks the transport loop to do next
+enum TaskOutcome {
+    Done,
+    // The session is initialized, so the server may now take a GET stream
+    OpenStream,
+}
+
+// State shared by every request of one connection
+struct HttpSession {
+    client: reqwest::Client,
+    config: HttpEndpoint,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+    events: mpsc::Sender<TransportEvent>,
+}
+
+impl HttpSession {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = self.config.headers.clone();
+        let session_id = self.session_id.lock().ok().and_then(|id| id.clone());
+        if let Some(value) = session_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+            headers.insert(SESSION_ID_HEADER, value);
+        }
+        let protocol_version = self.protocol_version.lock().ok().and_then(|v| v.clone());
+        if let Some(value) = protocol_version.and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(PROTOCOL_VERSION_HEADER, value);
+        }
+        headers
+    }
+
+    async fn deliver(&self, message: String) {
+        // The negotiated version has to be sent along with every later request
+        if let Ok(value) = serde_json::from_str::<Value>(&message) {
+            if let Some(version) = value
+                .get("result")
+                .filter(|result| result.get("capabilities").is_some())
+                .and_then(|result| result.get("protocolVersion"))
+                .and_then(Value::as_str)
+            {
+                if let Ok(mut protocol_version) = self.protocol_version.lock() {
+                    *protocol_version = Some(version.to_string());
+                }
+            }
+        }
+        let _ = self.events.send(TransportEvent::Message(message)).await;
+    }
+
+    async fn report_error(&self, error: String) {
+        let _ = self.events.send(TransportEvent::Error(error)).await;
+    }
+
+    // POSTs one message. The reply is sent as soon as the server has accepted it;
+    // a streamed response is read afterwards.
+    async fn post(
+        self: Arc<Self>,
+        message: String,
+        reply: tokio::sync::oneshot::Sender<Result<(), String>>,
+    ) -> TaskOutcome {
+        let opens_stream = is_initialized_notification(&message);
+        let sent_session = self.session_id.lock().ok().and_then(|id| id.clone());
+        let result = self
+            .client
+            .post(self.config.url.clone())
+            .headers(self.headers())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .header(CONTENT_TYPE, "application/json")
+            .body(message)
+            .send()
+            .await;
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                let _ = reply.send(Err(format!("Request failed: {}", e)));
+                return TaskOutcome::Done;
+            }
+        };
+        if let Some(id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            if let Ok(mut session_id) = self.session_id.lock() {
+                *session_id = Some(id.to_string());
+            }
+        }
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND && sent_session.is_some() {
+            // The server dropped the session; the next initialize starts a new one
+            if let Ok(mut session_id) = self.session_id.lock() {
+                *session_id = None;
+            }
+            let _ = reply.send(Err(
+                "Session expired on the server, the connection needs to be initialized again."
+                    .to_string(),
+            ));
+            return TaskOutcome::Done;
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let _ = reply.send(Err(format!(
+                "Server responded with {}: {}",
+                status,
+                body.trim()
+            )));
+            return TaskOutcome::Done;
+        }
+        let _ = reply.send(Ok(()));
+
+        if status == StatusCode::ACCEPTED {
+            // Nothing to read for notifications and responses
+        } else if is_event_stream(&response) {
+            let mut last_event_id = None;
+            let mut retry = None;
+            if let Err(e) = self
+                .read_stream(response, &mut last_event_id, &mut retry)
+                .await
+            {
+                match last_event_id {
+                    // The server numbered its events, so the rest can be replayed
+                    Some(last_event_id) => {
+                        self.listen(Some(last_event_id), retry, true).await;
+                    }
+                    None => {
+                        self.report_error(format!("Response stream broke: {}", e))
+                            .await
+                    }
+                }
+            }
+        } else {
+            match response.text().await {
+                Ok(body) => self.deliver_json_body(&body).await,
+                Err(e) => {
+                    self.report_error(format!("Failed to read response: {}", e))
+                        .await
+                }
+            }
+        }
+        if opens_stream {
+            TaskOutcome::OpenStream
+        } else {
+            TaskOutcome::Done
+        }
+    }
+
+    async fn deliver_json_body(&self, body: &str) {
+        let body = body.trim();
+        if body.is_empty() {
+            return;
+        }
+        match serde_json::from_str::<Value>(body) {
+            // A batch of responses is relayed message by message
+            Ok(Value::Array(messages)) => {
+                for message in messages {
+                    self.deliver(message.to_string()).await;
+                }
+            }
+            _ => self.deliver(body.to_string()).await,
+        }
+    }
+
+    // Relays every event of an SSE body. Errors if the stream broke off rather than
+    // being ended by the server.
+    async fn read_stream(
+        &self,
+        response: Response,
+        last_event_id: &mut Option<String>,
+        retry: &mut Option<Duration>,
+    ) -> Result<(), String> {
+        let mut parser = SseParser::default();
+        let mut body = response.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| e.to_string())?;
+            for event in parser.feed(&chunk) {
+                if let Some(id) = event.id {
+                    *last_event_id = Some(id);
+                }
+                if let Some(delay) = event.retry {
+                    *retry = Some(Duration::from_millis(delay));
+                }
+                if event.event == "message" && !event.data.trim().is_empty() {
+                    self.deliver(event.data).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Reads server-to-client messages from a GET stream, reconnecting with
+    // Last-Event-ID when it drops. When `resuming` a broken POST response, stops
+    // once the server ends the stream; the standalone stream is kept open.
+    async fn listen(
+        &self,
+        mut last_event_id: Option<String>,
+        mut retry: Option<Duration>,
+        resuming: bool,
+    ) -> TaskOutcome {
+        let mut failures = 0;
+        loop {
+            let mut request = self
+                .client
+                .get(self.config.url.clone())
+                .headers(self.headers())
+                .header(ACCEPT, "text/event-stream");
+            if let Some(id) = &last_event_id {
+                request = request.header(LAST_EVENT_ID_HEADER, id);
+            }
+
+            match request.send().await {
+                Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => {
+                    // The server does not offer a standalone stream
+                    if resuming {
+                        self.report_error("Server does not support resuming streams".into())
+                            .await;
+                    }
+                    return TaskOutcome::Done;
+                }
+                Ok(response) if response.status().is_success() && is_event_stream(&response) => {
+                    failures = 0;
+                    match self
+                        .read_stream(response, &mut last_event_id, &mut retry)
+                        .await
+                    {
+                        Ok(()) if resuming => return TaskOutcome::Done,
+                        Ok(()) => {}
+                        Err(e) => eprintln!("SSE stream from {} broke: {}", self.config.url, e),
+                    }
+                }
+                Ok(response) => {
+                    failures += 1;
+                    eprintln!(
+                        "SSE stream from {} refused with {}.",
+                        self.config.url,
+                        response.status()
+                    );
+                }
+                Err(e) => {
+                    failures += 1;
+                    eprintln!("Failed to open SSE stream from {}: {}", self.config.url, e);
+                }
+            }
+
+            if failures >= MAX_STREAM_RECONNECTS {
+                self.report_error(format!(
+                    "Giving up on the event stream after {} failed attempts",
+                    failures
+                ))
+                .await;
+                return TaskOutcome::Done;
+            }
+            tokio::time::sleep(retry.unwrap_or(DEFAULT_STREAM_RETRY)).await;
+        }
+    }
+
+    // Ends the session on the server, if it gave us one.
+    async fn terminate(&self) {
+        if self
+            .session_id
+            .lock()
+            .ok()
+            .and_then(|id| id.clone())
+            .is_none()
+        {
+            return;
+        }
+        let result = self
+            .client
+            .delete(self.config.url.clone())
+            .headers(self.headers())
+            .timeout(SESSION_TERMINATE_TIMEOUT)
+            .send()
+            .await;
+        if let Err(e) = result {
+            eprintln!("Failed to end session with {}: {}", self.config.url, e);
+        }
+    }
+}
+
+fn is_initialized_notification(message: &str) -> bool {
+    serde_json::from_str::<Value>(message)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("method")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .is_some_and(|method| method == "notifications/initialized")
+}
+
+/// Transport task of a Streamable HTTP connection: POSTs every outgoing message,
+/// relays JSON and SSE responses, and keeps the server's standalone event stream
+/// open once the session is initialized. Ends the session when stopped.
+pub async fn run_streamable_http(config: HttpEndpoint, channels: TransportChannels) {
+    let TransportChannels {
+        mut outgoing,
+        mut stop,
+        events,
+    } = channels;
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            let _ = events.send(TransportEvent::Closed(e)).await;
+            return;
+        }
+    };
+    let session = Arc::new(HttpSession {
+        client,
+        config,
+        session_id: Mutex::new(None),
+        protocol_version: Mutex::new(None),
+        events: events.clone(),
+    });
+
+    // Dropping the set aborts requests and streams still in flight
+    let mut tasks = JoinSet::new();
+    let mut stream_opened = false;
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => match message {
+                Some(OutgoingMessage { message, reply }) => {
+                    tasks.spawn(session.clone().post(message, reply));
+                }
+                None => break,
+            },
+            Some(outcome) = tasks.join_next() => {
+                if let Ok(TaskOutcome::OpenStream) = outcome {
+                    if !stream_opened {
+                        stream_opened = true;
+                        let session = session.clone();
+                        tasks.spawn(async move { session.listen(None, None, false).await });
+                    }
+                }
+            }
+            _ = &mut stop => break,
+        }
+    }
+
+    tasks.shutdown().await;
+    session.terminate().await;
+    let _ = events
+        .send(TransportEvent::Closed("Connection closed".to_string()))
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    // Minimal stand-in for a Streamable HTTP server: answers `initialize` with JSON
+    // and a session id, and every other request with an SSE stream.
+    async fn serve(listener: TcpListener, seen: mpsc::Sender<String>) {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let (head, body) = loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break (head.to_string(), body.to_string());
+                    }
+                }
+            };
+            let method = head.split(' ').next().unwrap().to_string();
+            let session = head.lines().find_map(|line| {
+                line.to_lowercase()
+                    .strip_prefix("mcp-session-id:")
+                    .map(|v| v.trim().to_string())
+            });
+            seen.send(format!("{} {:?}", method, session))
+                .await
+                .unwrap();
+
+            let response = match method.as_str() {
+                "POST" if body.contains("\"initialize\"") => {
+                    let body = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{}}}"#;
+                    format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nMcp-Session-Id: abc\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+                }
+                "POST" if !body.contains("\"id\"") => {
+                    "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                }
+                "POST" => {
+                    let body = "id: 1\r\ndata: {\"jsonrpc\":\"2.0\",\r\ndata: \"id\":2,\"result\":{}}\r\n\r\n";
+                    format!("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{}", body)
+                }
+                _ => "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            };
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    async fn send(outgoing: &mpsc::Sender<OutgoingMessage>, message: &str) -> Result<(), String> {
+        let (reply, result) = oneshot::channel();
+        outgoing
+            .send(OutgoingMessage {
+                message: message.to_string(),
+                reply,
+            })
+            .await
+            .unwrap();
+        result.await.unwrap()
+    }
+
+    async fn next_message(events: &mut mpsc::Receiver<TransportEvent>) -> String {
+        match events.recv().await {
+            Some(TransportEvent::Message(message)) => message,
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_session_and_relays_json_and_sse_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let (seen_sender, mut seen) = mpsc::channel(16);
+        tokio::spawn(serve(listener, seen_sender));
+
+        let (outgoing_sender, outgoing) = mpsc::channel(8);
+        let (stop_signal, stop) = oneshot::channel();
+        let (events_sender, mut events) = mpsc::channel(8);
+        let config = HttpEndpoint::new(&url, &HashMap::new()).unwrap();
+        tokio::spawn(run_streamable_http(
+            config,
+            TransportChannels {
+                outgoing,
+                stop,
+                events: events_sender,
+            },
+        ));
+
+        send(
+            &outgoing_sender,
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#,
+        )
+        .await
+        .unwrap();
+        assert!(next_message(&mut events).await.contains("protocolVersion"));
+        assert_eq!(seen.recv().await.unwrap(), "POST None");
+
+        send(
+            &outgoing_sender,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(seen.recv().await.unwrap(), r#"POST Some("abc")"#);
+        // The standalone stream is requested once the session is initialized
+        assert_eq!(seen.recv().await.unwrap(), r#"GET Some("abc")"#);
+
+        send(
+            &outgoing_sender,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            next_message(&mut events).await,
+            "{\"jsonrpc\":\"2.0\",\n\"id\":2,\"result\":{}}"
+        );
+
+        stop_signal.send(()).unwrap();
+        assert_eq!(seen.recv().await.unwrap(), r#"POST Some("abc")"#);
+        assert_eq!(seen.recv().await.unwrap(), r#"DELETE Some("abc")"#);
+        assert!(matches!(
+            events.recv().await,
+            Some(TransportEvent::Closed(_))
+        ));
+    }
+}
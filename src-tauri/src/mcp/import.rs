@@ -0,0 +1,695 @@
+use crate::mcp::resolve::home_dir;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Client whose config file a server was imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigClient {
+    ClaudeDesktop,
+    Cursor,
+    Windsurf,
+    /// A workspace's `.vscode/mcp.json`.
+    VsCode,
+    /// Any other file with an `mcpServers` or `servers` block.
+    File,
+}
+
+impl std::fmt::Display for ConfigClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConfigClient::ClaudeDesktop => "Claude Desktop",
+            ConfigClient::Cursor => "Cursor",
+            ConfigClient::Windsurf => "Windsurf",
+            ConfigClient::VsCode => "VS Code",
+            ConfigClient::File => "config file",
+        })
+    }
+}
+
+/// A server in Daan's terms. Mirrors the `stdio`, `sse`, `streamableHttp` and
+/// `websocket` variants of `McpServerConfig` in `src/store/mcp.ts`, so the frontend's
+/// existing servers can be deserialized as one too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ImportedConfig {
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<String>,
+    },
+    Sse {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    StreamableHttp {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    #[serde(rename = "websocket")]
+    WebSocket {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl ImportedConfig {
+    // Servers that start the same command or reach the same URL are the same server,
+    // whatever their environment
+    fn same_server(&self, other: &ImportedConfig) -> bool {
+        match (self, other) {
+            (
+                ImportedConfig::Stdio { command, args, .. },
+                ImportedConfig::Stdio {
+                    command: other_command,
+                    args: other_args,
+                    ..
+                },
+            ) => command == other_command && args == other_args,
+            (ImportedConfig::Stdio { .. }, _) | (_, ImportedConfig::Stdio { .. }) => false,
+            _ => self.url() == other.url(),
+        }
+    }
+
+    fn url(&self) -> Option<&str> {
+        match self {
+            ImportedConfig::Stdio { .. } => None,
+            ImportedConfig::Sse { url, .. }
+            | ImportedConfig::StreamableHttp { url, .. }
+            | ImportedConfig::WebSocket { url, .. } => Some(url.trim_end_matches('/')),
+        }
+    }
+}
+
+/// A server Daan already has, sent by the frontend to detect duplicates.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExistingServer {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ImportedConfig,
+}
+
+/// A config file that was looked at.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSource {
+    pub client: ConfigClient,
+    pub path: String,
+    pub found: bool,
+    /// Why the file could not be read or parsed.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedServer {
+    pub name: String,
+    pub client: ConfigClient,
+    pub source: String,
+    pub config: ImportedConfig,
+    /// Things that did not carry over exactly, e.g. unresolved `${input:...}` values.
+    pub notes: Vec<String>,
+}
+
+/// An entry left out because an equivalent server exists or was imported already.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateServer {
+    pub name: String,
+    pub client: ConfigClient,
+    pub source: String,
+    /// Daan server or earlier import it duplicates, e.g. `"filesystem" (Cursor)`.
+    pub duplicate_of: String,
+}
+
+/// An entry Daan cannot run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsupportedServer {
+    pub name: String,
+    pub client: ConfigClient,
+    pub source: String,
+    pub reason: String,
+}
+
+/// Result of `import_servers`; nothing is added to Daan until the frontend does so.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub sources: Vec<ConfigSource>,
+    pub servers: Vec<ImportedServer>,
+    pub duplicates: Vec<DuplicateServer>,
+    pub unsupported: Vec<UnsupportedServer>,
+}
+
+/// Per-user config files of the clients Daan can import from, whether they exist
+/// or not.
+pub fn known_config_paths() -> Vec<(ConfigClient, PathBuf)> {
+    let Some(home) = home_dir() else {
+        return Vec::new();
+    };
+    let claude_dir = if cfg!(target_os = "macos") {
+        Some(home.join("Library").join("Application Support"))
+    } else if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        Some(
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".config")),
+        )
+    };
+    let mut paths = Vec::new();
+    if let Some(dir) = claude_dir {
+        paths.push((
+            ConfigClient::ClaudeDesktop,
+            dir.join("Claude").join("claude_desktop_config.json"),
+        ));
+    }
+    paths.push((ConfigClient::Cursor, home.join(".cursor").join("mcp.json")));
+    paths.push((
+        ConfigClient::Windsurf,
+        home.join(".codeium")
+            .join("windsurf")
+            .join("mcp_config.json"),
+    ));
+    paths
+}
+
+/// Where to look for a path picked by the user: a directory is taken as a
+/// workspace with a `.vscode/mcp.json`, a file is read as it is.
+pub fn source_for_path(path: &Path) -> Result<(ConfigClient, PathBuf), String> {
+    if !path.is_absolute() {
+        return Err(format!(
+            "Import path '{}' must be absolute.",
+            path.display()
+        ));
+    }
+    if path.is_dir() {
+        return Ok((ConfigClient::VsCode, path.join(".vscode").join("mcp.json")));
+    }
+    let in_vscode_dir = path.parent().and_then(Path::file_name) == Some(".vscode".as_ref());
+    let client = if in_vscode_dir {
+        ConfigClient::VsCode
+    } else {
+        ConfigClient::File
+    };
+    Ok((client, path.to_path_buf()))
+}
+
+/// Reads the `mcpServers` (or VS Code `servers`) blocks of `sources` and turns
+/// their entries into Daan server configs, setting aside duplicates of `existing`
+/// servers and of each other as well as entries Daan cannot run.
+pub fn import_servers(
+    sources: &[(ConfigClient, PathBuf)],
+    existing: &[ExistingServer],
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    for (client, path) in sources {
+        let source = path.to_string_lossy().into_owned();
+        let mut info = ConfigSource {
+            client: *client,
+            path: source.clone(),
+            found: path.is_file(),
+            error: None,
+        };
+        if info.found {
+            match read_server_block(path) {
+                Ok(entries) => {
+                    let context = Context::new(*client, path);
+                    for (name, entry) in entries {
+                        import_entry(&mut report, &context, &source, name, &entry, existing);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to import MCP servers from {}: {}", source, e);
+                    info.error = Some(e);
+                }
+            }
+        }
+        report.sources.push(info);
+    }
+    report
+}
+
+fn import_entry(
+    report: &mut ImportReport,
+    context: &Context,
+    source: &str,
+    name: String,
+    entry: &Value,
+    existing: &[ExistingServer],
+) {
+    let (config, notes) = match parse_entry(entry, context) {
+        Ok(parsed) => parsed,
+        Err(reason) => {
+            report.unsupported.push(UnsupportedServer {
+                name,
+                client: context.client,
+                source: source.to_string(),
+                reason,
+            });
+            return;
+        }
+    };
+    let duplicate_of = existing
+        .iter()
+        .find(|server| server.config.same_server(&config))
+        .map(|server| format!("\"{}\" in Daan", server.name))
+        .or_else(|| {
+            report
+                .servers
+                .iter()
+                .find(|server| server.config.same_server(&config))
+                .map(|server| format!("\"{}\" ({})", server.name, server.client))
+        });
+    match duplicate_of {
+        Some(duplicate_of) => report.duplicates.push(DuplicateServer {
+            name,
+            client: context.client,
+            source: source.to_string(),
+            duplicate_of,
+        }),
+        None => report.servers.push(ImportedServer {
+            name,
+            client: context.client,
+            source: source.to_string(),
+            config,
+            notes,
+        }),
+    }
+}
+
+// The entries of the server block, by name
+fn read_server_block(path: &Path) -> Result<Vec<(String, Value)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    // VS Code allows comments and trailing commas in its JSON files
+    let root: Value =
+        serde_json::from_str(&strip_jsonc(&text)).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let block = root
+        .get("mcpServers")
+        .or_else(|| root.get("servers"))
+        .or_else(|| root.get("mcp").and_then(|mcp| mcp.get("servers")))
+        .ok_or_else(|| "No \"mcpServers\" or \"servers\" block.".to_string())?;
+    match block {
+        Value::Object(entries) => Ok(entries
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect()),
+        _ => Err("The server block is not an object.".to_string()),
+    }
+}
+
+// Variables some clients expand in their configs
+struct Context {
+    client: ConfigClient,
+    workspace: Option<String>,
+    home: Option<String>,
+}
+
+impl Context {
+    fn new(client: ConfigClient, path: &Path) -> Self {
+        // `<workspace>/.vscode/mcp.json`
+        let workspace = (client == ConfigClient::VsCode)
+            .then(|| path.parent()?.parent())
+            .flatten()
+            .map(|dir| dir.to_string_lossy().into_owned());
+        Self {
+            client,
+            workspace,
+            home: home_dir().map(|home| home.to_string_lossy().into_owned()),
+        }
+    }
+
+    fn expand(&self, value: &str) -> String {
+        let mut value = value.to_string();
+        if let Some(workspace) = &self.workspace {
+            value = value.replace("${workspaceFolder}", workspace);
+        }
+        if let Some(home) = &self.home {
+            value = value.replace("${userHome}", home);
+        }
+        value
+    }
+}
+
+// `type` values of servers reached over the network
+const URL_TYPES: &[&str] = &["sse", "http", "streamable-http", "streamableHttp", "ws"];
+
+fn parse_entry(entry: &Value, context: &Context) -> Result<(ImportedConfig, Vec<String>), String> {
+    let entry = entry
+        .as_object()
+        .ok_or_else(|| "The entry is not an object.".to_string())?;
+    let kind = entry.get("type").and_then(Value::as_str);
+    let command = entry.get("command").and_then(Value::as_str);
+    let is_stdio = match kind {
+        None => command.is_some(),
+        Some("stdio") => true,
+        Some(kind) if URL_TYPES.contains(&kind) => false,
+        Some(kind) => return Err(format!("Unsupported server type '{}'.", kind)),
+    };
+    let mut notes = Vec::new();
+    let config = if is_stdio {
+        let command = command.ok_or_else(|| "A stdio server without a command.".to_string())?;
+        parse_stdio(command, entry, context, &mut notes)?
+    } else {
+        let url = entry
+            .get("url")
+            .or_else(|| entry.get("serverUrl"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| "Neither a command nor a URL.".to_string())?;
+        parse_remote(url, kind, entry, context)?
+    };
+
+    if entry.get("disabled").and_then(Value::as_bool) == Some(true) {
+        notes.push(format!("Disabled in {}.", context.client));
+    }
+    if serde_json::to_string(&config).is_ok_and(|json| json.contains("${")) {
+        notes.push(
+            "Contains placeholders like ${input:...}; replace them with real values.".to_string(),
+        );
+    }
+    Ok((config, notes))
+}
+
+fn parse_stdio(
+    command: &str,
+    entry: &Map<String, Value>,
+    context: &Context,
+    notes: &mut Vec<String>,
+) -> Result<ImportedConfig, String> {
+    if entry.contains_key("envFile") {
+        notes.push("envFile is not supported; add its variables to the environment.".to_string());
+    }
+    Ok(ImportedConfig::Stdio {
+        command: context.expand(command),
+        args: string_list(entry.get("args"), "args")?
+            .iter()
+            .map(|arg| context.expand(arg))
+            .collect(),
+        env: string_map(entry.get("env"), "env")?
+            .into_iter()
+            .map(|(key, value)| (key, context.expand(&value)))
+            .collect(),
+        cwd: entry
+            .get("cwd")
+            .and_then(Value::as_str)
+            .map(|cwd| context.expand(cwd)),
+    })
+}
+
+// ws:// URLs become WebSocket servers. HTTP servers declared as `http` are
+// Streamable HTTP servers; the rest, including entries without a type, use SSE
+fn parse_remote(
+    url: &str,
+    kind: Option<&str>,
+    entry: &Map<String, Value>,
+    context: &Context,
+) -> Result<ImportedConfig, String> {
+    let url = context.expand(url);
+    let headers = string_map(entry.get("headers"), "headers")?
+        .into_iter()
+        .map(|(name, value)| (name, context.expand(&value)))
+        .collect();
+    let scheme = url
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+        Some("ws" | "wss") => Ok(ImportedConfig::WebSocket { url, headers }),
+        Some("http" | "https")
+            if matches!(kind, Some("http" | "streamable-http" | "streamableHttp")) =>
+        {
+            Ok(ImportedConfig::StreamableHttp { url, headers })
+        }
+        Some("http" | "https") => Ok(ImportedConfig::Sse { url, headers }),
+        _ => Err(format!("Unsupported URL '{}'.", url)),
+    }
+}
+
+// Numbers and booleans are accepted as strings, as the clients do
+fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn string_list(value: Option<&Value>, field: &str) -> Result<Vec<String>, String> {
+    match value {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                scalar_string(item)
+                    .ok_or_else(|| format!("\"{}\" holds a non-string value.", field))
+            })
+            .collect(),
+        Some(_) => Err(format!("\"{}\" is not a list.", field)),
+    }
+}
+
+fn string_map(value: Option<&Value>, field: &str) -> Result<BTreeMap<String, String>, String> {
+    match value {
+        None | Some(Value::Null) => Ok(BTreeMap::new()),
+        Some(Value::Object(entries)) => entries
+            .iter()
+            .map(|(key, value)| {
+                scalar_string(value)
+                    .map(|value| (key.clone(), value))
+                    .ok_or_else(|| format!("\"{}.{}\" is not a string.", field, key))
+            })
+            .collect(),
+        Some(_) => Err(format!("\"{}\" is not an object.", field)),
+    }
+}
+
+// Removes comments and trailing commas, leaving strings untouched
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = '\0';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            (']' | '}', _) => {
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("daan-import-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn imports_mcp_servers_blocks() {
+        let path = write_config(
+            "claude_desktop_config.json",
+            r#"{
+              "mcpServers": {
+                "filesystem": {
+                  "command": "npx",
+                  "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"],
+                  "env": { "DEBUG": 1 }
+                },
+                "remote": { "serverUrl": "https://example.com/sse", "disabled": true },
+                "broken": { "args": ["x"] },
+                "grpc": { "type": "grpc", "url": "grpc://example.com" }
+              }
+            }"#,
+        );
+        let report = import_servers(&[(ConfigClient::ClaudeDesktop, path.clone())], &[]);
+
+        assert!(report.sources[0].found);
+        assert_eq!(report.servers.len(), 2);
+        assert_eq!(
+            report.servers[0].config,
+            ImportedConfig::Stdio {
+                command: "npx".to_string(),
+                args: vec![
+                    "-y".to_string(),
+                    "@modelcontextprotocol/server-filesystem".to_string(),
+                    "/tmp".to_string()
+                ],
+                env: BTreeMap::from([("DEBUG".to_string(), "1".to_string())]),
+                cwd: None,
+            }
+        );
+        assert_eq!(report.servers[1].name, "remote");
+        assert_eq!(
+            report.servers[1].config,
+            ImportedConfig::Sse {
+                url: "https://example.com/sse".to_string(),
+                headers: BTreeMap::new(),
+            }
+        );
+        assert_eq!(report.servers[1].notes, vec!["Disabled in Claude Desktop."]);
+
+        let reasons: Vec<_> = report
+            .unsupported
+            .iter()
+            .map(|server| (server.name.as_str(), server.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("broken", "Neither a command nor a URL."),
+                ("grpc", "Unsupported server type 'grpc'.")
+            ]
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn imports_vscode_workspace_configs() {
+        let path = write_config(
+            ".vscode/mcp.json",
+            r#"{
+              // Shared with the team
+              "inputs": [{ "id": "token", "type": "promptString" }],
+              "servers": {
+                "local": {
+                  "type": "stdio",
+                  "command": "${workspaceFolder}/bin/server", /* built by make */
+                  "env": { "TOKEN": "${input:token}" },
+                },
+                "api": {
+                  "type": "http",
+                  "url": "https://example.com/mcp",
+                  "headers": { "Authorization": "Bearer x" }
+                },
+              },
+            }"#,
+        );
+        let workspace = path.parent().unwrap().parent().unwrap();
+        let source = source_for_path(workspace).unwrap();
+        assert_eq!(source, (ConfigClient::VsCode, path.clone()));
+        let report = import_servers(&[source], &[]);
+        assert_eq!(report.sources[0].error, None);
+
+        let local = report.servers.iter().find(|s| s.name == "local").unwrap();
+        match &local.config {
+            ImportedConfig::Stdio { command, .. } => {
+                assert_eq!(command, &format!("{}/bin/server", workspace.display()))
+            }
+            config => panic!("not a stdio server: {:?}", config),
+        }
+        assert!(local.notes[0].contains("placeholders"));
+
+        let api = report.servers.iter().find(|s| s.name == "api").unwrap();
+        assert_eq!(
+            api.config,
+            ImportedConfig::StreamableHttp {
+                url: "https://example.com/mcp".to_string(),
+                headers: BTreeMap::from([("Authorization".to_string(), "Bearer x".to_string())]),
+            }
+        );
+        assert!(api.notes.is_empty());
+        let _ = std::fs::remove_dir_all(workspace);
+    }
+
+    #[test]
+    fn reports_duplicates_of_existing_and_imported_servers() {
+        let first = write_config(
+            "mcp.json",
+            r#"{ "mcpServers": {
+              "fs": { "command": "npx", "args": ["server-fs"] },
+              "search": { "url": "https://example.com/sse/" }
+            } }"#,
+        );
+        let second = write_config(
+            "mcp_config.json",
+            r#"{ "mcpServers": { "files": { "command": "npx", "args": ["server-fs"], "env": { "A": "b" } } } }"#,
+        );
+        // As sent by the frontend, with fields the import does not know
+        let existing: Vec<ExistingServer> = serde_json::from_str(
+            r#"[{ "id": "custom::1", "name": "Search", "type": "sse",
+                  "url": "https://example.com/sse", "enabled": true }]"#,
+        )
+        .unwrap();
+
+        let report = import_servers(
+            &[
+                (ConfigClient::Cursor, first.clone()),
+                (ConfigClient::Windsurf, second.clone()),
+            ],
+            &existing,
+        );
+        assert_eq!(report.servers.len(), 1);
+        assert_eq!(report.servers[0].name, "fs");
+        let duplicates: Vec<_> = report
+            .duplicates
+            .iter()
+            .map(|d| (d.name.as_str(), d.duplicate_of.as_str()))
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![
+                ("search", "\"Search\" in Daan"),
+                ("files", "\"fs\" (Cursor)")
+            ]
+        );
+        let _ = std::fs::remove_dir_all(first.parent().unwrap());
+        let _ = std::fs::remove_dir_all(second.parent().unwrap());
+    }
+
+    #[test]
+    fn strips_comments_and_trailing_commas() {
+        let stripped = strip_jsonc(
+            r#"{"a": "// not a comment", /* x */ "b": [1, 2,], // y
+        }"#,
+        );
+        let value: Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value["a"], "// not a comment");
+        assert_eq!(value["b"], serde_json::json!([1, 2]));
+    }
+}
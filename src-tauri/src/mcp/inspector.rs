@@ -0,0 +1,607 @@
+use crate::mcp::control::now_millis;
+use crate::mcp::events::EventSink;
+use crate::mcp::recorder::{Direction, TrafficRecorder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Exchanges kept across all servers before the oldest are dropped.
+pub const INSPECTOR_CAPACITY: usize = 2000;
+
+/// Captured messages kept across all servers, in bytes, before the oldest
+/// exchanges are dropped.
+pub const INSPECTOR_BYTE_BUDGET: usize = 32 * 1024 * 1024;
+
+/// Larger messages are kept as a truncated string preview.
+pub const MAX_CAPTURED_MESSAGE_SIZE: usize = 256 * 1024;
+
+/// Page size of `TrafficInspector::query` when no limit is given.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExchangeKind {
+    /// A request, with its response once it arrived.
+    Request,
+    Notification,
+    /// A response to no request seen by the inspector.
+    Response,
+    /// Not a JSON-RPC message.
+    Invalid,
+}
+
+/// One request/response pair, notification or stray message of one server.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exchange {
+    /// Increases with every captured exchange, across all servers.
+    pub seq: u64,
+    /// Id of the process or remote connection.
+    pub source_id: String,
+    pub kind: ExchangeKind,
+    /// Direction of the first message: `outbound` for calls made by Daan.
+    pub direction: Direction,
+    pub method: Option<String>,
+    /// JSON-RPC id of the request or response.
+    pub id: Option<Value>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub message: Value,
+    pub response: Option<Value>,
+    pub responded_at: Option<u64>,
+    pub latency_ms: Option<u64>,
+    /// `error` of the response, if it failed.
+    pub error: Option<Value>,
+    /// Bytes of `message` and `response` counted against the byte budget.
+    #[serde(skip)]
+    size: usize,
+}
+
+/// Live update sent to subscribers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event", content = "data")]
+pub enum InspectorUpdate {
+    Added(Exchange),
+    /// A request got its response.
+    Completed(Exchange),
+}
+
+/// Filter and position of a page of exchanges, newest first.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficQuery {
+    #[serde(default)]
+    pub source_id: Option<String>,
+    /// Only exchanges older than this `seq`, for the next page.
+    #[serde(default)]
+    pub before: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub kind: Option<ExchangeKind>,
+    /// Only requests that failed and invalid messages.
+    #[serde(default)]
+    pub errors_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficPage {
+    pub exchanges: Vec<Exchange>,
+    /// `before` of the next page, if there is one.
+    pub next_before: Option<u64>,
+}
+
+struct Subscriber {
+    id: u64,
+    source_id: Option<String>,
+    sink: Box<dyn EventSink<InspectorUpdate>>,
+}
+
+#[derive(Default)]
+struct InspectorState {
+    exchanges: VecDeque<Exchange>,
+    // Sum of the `size` of `exchanges`
+    retained_bytes: usize,
+    next_seq: u64,
+    // (source, direction of the request, request id) -> seq of the waiting request
+    pending: HashMap<(String, Direction, String), u64>,
+    subscribers: Vec<Subscriber>,
+    next_subscriber: u64,
+}
+
+/// Captures the JSON-RPC traffic of every process and remote connection, pairs
+/// requests with their responses and measures the latency, for the inspector UI.
+#[derive(Clone, Default)]
+pub struct TrafficInspector(Arc<Mutex<InspectorState>>);
+
+impl TrafficInspector {
+    /// Records a message sent to or received from `source_id`. Batches are split
+    /// into their messages.
+    pub fn capture(&self, source_id: &str, direction: Direction, raw: &str) {
+        let message = match serde_json::from_str::<Value>(raw) {
+            Ok(message) => message,
+            Err(_) => Value::String(preview(raw)),
+        };
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        match message {
+            Value::Array(batch) => {
+                for message in batch {
+                    state.capture_one(source_id, direction, message, raw.len());
+                }
+            }
+            message => state.capture_one(source_id, direction, message, raw.len()),
+        }
+    }
+
+    pub fn query(&self, query: &TrafficQuery) -> TrafficPage {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+        let Ok(state) = self.0.lock() else {
+            return TrafficPage {
+                exchanges: Vec::new(),
+                next_before: None,
+            };
+        };
+        let mut exchanges: Vec<Exchange> = state
+            .exchanges
+            .iter()
+            .rev()
+            .filter(|exchange| query.before.map_or(true, |before| exchange.seq < before))
+            .filter(|exchange| matches_query(exchange, query))
+            .take(limit + 1)
+            .cloned()
+            .collect();
+        let next_before = if exchanges.len() > limit {
+            exchanges.truncate(limit);
+            exchanges.last().map(|exchange| exchange.seq)
+        } else {
+            None
+        };
+        TrafficPage {
+            exchanges,
+            next_before,
+        }
+    }
+
+    /// Sends every new or completed exchange of `source_id` (or of all servers)
+    /// to `sink` until unsubscribed. Returns the subscription id.
+    pub fn subscribe(
+        &self,
+        source_id: Option<String>,
+        sink: impl EventSink<InspectorUpdate> + 'static,
+    ) -> u64 {
+        let Ok(mut state) = self.0.lock() else {
+            return 0;
+        };
+        state.next_subscriber += 1;
+        let id = state.next_subscriber;
+        state.subscribers.push(Subscriber {
+            id,
+            source_id,
+            sink: Box::new(sink),
+        });
+        id
+    }
+
+    /// Returns `false` if there was no such subscription.
+    pub fn unsubscribe(&self, subscription_id: u64) -> bool {
+        let Ok(mut state) = self.0.lock() else {
+            return false;
+        };
+        let before = state.subscribers.len();
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.id != subscription_id);
+        state.subscribers.len() != before
+    }
+
+    /// Drops the captured exchanges of `source_id`, or of every server.
+    pub fn clear(&self, source_id: Option<&str>) {
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        match source_id {
+            Some(source_id) => {
+                state
+                    .exchanges
+                    .retain(|exchange| exchange.source_id != source_id);
+                state
+                    .pending
+                    .retain(|(source, _, _), _| source != source_id);
+                state.retained_bytes = state.exchanges.iter().map(|e| e.size).sum();
+            }
+            None => {
+                state.exchanges.clear();
+                state.pending.clear();
+                state.retained_bytes = 0;
+            }
+        }
+    }
+}
+
+impl InspectorState {
+    fn capture_one(&mut self, source_id: &str, direction: Direction, message: Value, size: usize) {
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let id = message.get("id").filter(|id| !id.is_null()).cloned();
+        let now = now_millis();
+
+        let kind = match (&method, &id) {
+            (Some(_), Some(_)) => ExchangeKind::Request,
+            (Some(_), None) => ExchangeKind::Notification,
+            (None, Some(id)) => {
+                let key = (source_id.to_string(), opposite(direction), id.to_string());
+                if let Some(seq) = self.pending.remove(&key) {
+                    self.complete(seq, message, size, now);
+                    return;
+                }
+                ExchangeKind::Response
+            }
+            (None, None) => ExchangeKind::Invalid,
+        };
+
+        self.next_seq += 1;
+        let seq = self.next_seq;
+        if kind == ExchangeKind::Request {
+            if let Some(id) = &id {
+                self.pending
+                    .insert((source_id.to_string(), direction, id.to_string()), seq);
+            }
+        }
+        let error = (kind == ExchangeKind::Response)
+            .then(|| message.get("error").cloned())
+            .flatten();
+        let (message, size) = limit_size(message, size);
+        let exchange = Exchange {
+            seq,
+            source_id: source_id.to_string(),
+            kind,
+            direction,
+            method,
+            id,
+            timestamp: now,
+            message,
+            response: None,
+            responded_at: None,
+            latency_ms: None,
+            error,
+            size,
+        };
+
+        if self.exchanges.len() >= INSPECTOR_CAPACITY {
+            self.evict_oldest();
+        }
+        self.retained_bytes += size;
+        self.exchanges.push_back(exchange.clone());
+        self.enforce_byte_budget();
+        self.notify(InspectorUpdate::Added(exchange));
+    }
+
+    fn complete(&mut self, seq: u64, response: Value, size: usize, now: u64) {
+        let Ok(index) = self
+            .exchanges
+            .binary_search_by_key(&seq, |exchange| exchange.seq)
+        else {
+            return;
+        };
+        let (response, size) = limit_size(response, size);
+        let exchange = &mut self.exchanges[index];
+        exchange.error = response.get("error").cloned();
+        exchange.response = Some(response);
+        exchange.responded_at = Some(now);
+        exchange.latency_ms = Some(now.saturating_sub(exchange.timestamp));
+        exchange.size += size;
+        let exchange = exchange.clone();
+        self.retained_bytes += size;
+        self.enforce_byte_budget();
+        self.notify(InspectorUpdate::Completed(exchange));
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some(evicted) = self.exchanges.pop_front() {
+            self.retained_bytes -= evicted.size;
+            self.pending
+                .retain(|_, pending_seq| *pending_seq != evicted.seq);
+        }
+    }
+
+    // The newest exchange is kept even if it alone is over the budget
+    fn enforce_byte_budget(&mut self) {
+        while self.retained_bytes > INSPECTOR_BYTE_BUDGET && self.exchanges.len() > 1 {
+            self.evict_oldest();
+        }
+    }
+
+    fn notify(&mut self, update: InspectorUpdate) {
+        let source_id = match &update {
+            InspectorUpdate::Added(exchange) | InspectorUpdate::Completed(exchange) => {
+                exchange.source_id.clone()
+            }
+        };
+        // A subscriber whose channel is gone (e.g. the webview reloaded) is dropped
+        self.subscribers.retain(|subscriber| {
+            if subscriber
+                .source_id
+                .as_ref()
+                .is_some_and(|wanted| *wanted != source_id)
+            {
+                return true;
+            }
+            match subscriber.sink.send(update.clone()) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Dropping inspector subscription {}: {}", subscriber.id, e);
+                    false
+                }
+            }
+        });
+    }
+}
+
+fn matches_query(exchange: &Exchange, query: &TrafficQuery) -> bool {
+    if query
+        .source_id
+        .as_ref()
+        .is_some_and(|source_id| *source_id != exchange.source_id)
+    {
+        return false;
+    }
+    if query.kind.is_some_and(|kind| kind != exchange.kind) {
+        return false;
+    }
+    !query.errors_only || exchange.error.is_some() || exchange.kind == ExchangeKind::Invalid
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Outbound => Direction::Inbound,
+        Direction::Inbound => Direction::Outbound,
+    }
+}
+
+// Keeps huge messages (file contents, images) from filling memory. `size` is that
+// of the raw text, which may hold a whole batch. Returns the message with the size
+// it is counted as, which overestimates the messages of small batches.
+fn limit_size(message: Value, size: usize) -> (Value, usize) {
+    if size <= MAX_CAPTURED_MESSAGE_SIZE {
+        return (message, size);
+    }
+    let text = message.to_string();
+    if text.len() <= MAX_CAPTURED_MESSAGE_SIZE {
+        return (message, text.len());
+    }
+    let preview = preview(&text);
+    let size = preview.len();
+    (Value::String(preview), size)
+}
+
+fn preview(raw: &str) -> String {
+    if raw.len() <= MAX_CAPTURED_MESSAGE_SIZE {
+        return raw.to_string();
+    }
+    let mut end = MAX_CAPTURED_MESSAGE_SIZE;
+    while !raw.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}… ({} bytes)", &raw[..end], raw.len())
+}
+
+/// Everything that observes the messages exchanged with one server: its traffic
+/// recording, if enabled, and the inspector. Handed to the reader and writer of
+/// a process and to the relay of a remote connection.
+#[derive(Clone, Default)]
+pub struct TrafficTap {
+    source_id: String,
+    recorder: Option<TrafficRecorder>,
+    inspector: Option<TrafficInspector>,
+}
+
+impl TrafficTap {
+    pub fn new(source_id: impl Into<String>) -> Self {
+        Self {
+            source_id: source_id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_recorder(mut self, recorder: Option<TrafficRecorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    pub fn with_inspector(mut self, inspector: TrafficInspector) -> Self {
+        self.inspector = Some(inspector);
+        self
+    }
+
+    pub fn recorder(&self) -> Option<&TrafficRecorder> {
+        self.recorder.as_ref()
+    }
+
+    /// A message written to the server.
+    pub fn outbound(&self, message: &str) {
+        self.observe(Direction::Outbound, message);
+    }
+
+    /// A message read from the server.
+    pub fn inbound(&self, message: &str) {
+        self.observe(Direction::Inbound, message);
+    }
+
+    fn observe(&self, direction: Direction, message: &str) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(direction, message);
+        }
+        if let Some(inspector) = &self.inspector {
+            inspector.capture(&self.source_id, direction, message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn pairs_requests_with_responses() {
+        let inspector = TrafficInspector::default();
+        inspector.capture(
+            "a",
+            Direction::Outbound,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#,
+        );
+        inspector.capture(
+            "b",
+            Direction::Inbound,
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#,
+        );
+        inspector.capture(
+            "a",
+            Direction::Inbound,
+            r#"{"jsonrpc":"2.0","method":"notifications/message"}"#,
+        );
+        inspector.capture(
+            "a",
+            Direction::Inbound,
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-1,"message":"no"}}"#,
+        );
+
+        let page = inspector.query(&TrafficQuery::default());
+        let kinds: Vec<_> = page
+            .exchanges
+            .iter()
+            .map(|e| (e.source_id.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a", ExchangeKind::Notification),
+                // The response from `b` does not answer the request to `a`
+                ("b", ExchangeKind::Response),
+                ("a", ExchangeKind::Request),
+            ]
+        );
+        let request = &page.exchanges[2];
+        assert_eq!(request.method.as_deref(), Some("tools/list"));
+        assert!(request.latency_ms.is_some());
+        assert_eq!(request.error.as_ref().unwrap()["message"], "no");
+
+        let errors = inspector.query(&TrafficQuery {
+            errors_only: true,
+            ..Default::default()
+        });
+        assert_eq!(errors.exchanges.len(), 1);
+    }
+
+    #[test]
+    fn pages_from_newest_to_oldest() {
+        let inspector = TrafficInspector::default();
+        for i in 0..5 {
+            let message = format!(r#"{{"jsonrpc":"2.0","method":"n{}"}}"#, i);
+            inspector.capture("a", Direction::Inbound, &message);
+        }
+
+        let query = TrafficQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = inspector.query(&query);
+        let second = inspector.query(&TrafficQuery {
+            before: first.next_before,
+            ..query.clone()
+        });
+        let third = inspector.query(&TrafficQuery {
+            before: second.next_before,
+            ..query
+        });
+        let methods = |page: &TrafficPage| -> Vec<String> {
+            page.exchanges
+                .iter()
+                .filter_map(|e| e.method.clone())
+                .collect()
+        };
+        assert_eq!(methods(&first), ["n4", "n3"]);
+        assert_eq!(methods(&second), ["n2", "n1"]);
+        assert_eq!(methods(&third), ["n0"]);
+        assert_eq!(third.next_before, None);
+    }
+
+    #[test]
+    fn drops_the_oldest_exchanges_over_the_byte_budget() {
+        let inspector = TrafficInspector::default();
+        let payload = "x".repeat(MAX_CAPTURED_MESSAGE_SIZE - 100);
+        let count = INSPECTOR_BYTE_BUDGET / MAX_CAPTURED_MESSAGE_SIZE + 10;
+        for i in 0..count {
+            let request = format!(
+                r#"{{"jsonrpc":"2.0","id":{},"method":"read","params":"{}"}}"#,
+                i, payload
+            );
+            inspector.capture("a", Direction::Outbound, &request);
+        }
+        // Responses count too, and one to an evicted request is a stray response
+        for id in [0, count - 1] {
+            let response = format!(r#"{{"jsonrpc":"2.0","id":{},"result":"{}"}}"#, id, payload);
+            inspector.capture("a", Direction::Inbound, &response);
+        }
+
+        let state = inspector.0.lock().unwrap();
+        assert!(state.retained_bytes <= INSPECTOR_BYTE_BUDGET);
+        assert_eq!(
+            state.retained_bytes,
+            state.exchanges.iter().map(|e| e.size).sum::<usize>()
+        );
+        assert!(state.exchanges.len() < count);
+        let newest = state.exchanges.back().unwrap();
+        assert_eq!(newest.kind, ExchangeKind::Response);
+        let last_request = &state.exchanges[state.exchanges.len() - 2];
+        assert_eq!(last_request.id, Some(Value::from(count - 1)));
+        assert!(last_request.response.is_some());
+        drop(state);
+
+        inspector.clear(Some("a"));
+        assert_eq!(inspector.0.lock().unwrap().retained_bytes, 0);
+    }
+
+    #[test]
+    fn notifies_subscribers_of_their_source() {
+        let inspector = TrafficInspector::default();
+        let (sender, mut updates) = mpsc::unbounded_channel();
+        let subscription = inspector.subscribe(Some("a".to_string()), sender);
+
+        inspector.capture(
+            "b",
+            Direction::Outbound,
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+        );
+        inspector.capture(
+            "a",
+            Direction::Outbound,
+            r#"{"jsonrpc":"2.0","id":"x","method":"ping"}"#,
+        );
+        inspector.capture(
+            "a",
+            Direction::Inbound,
+            r#"{"jsonrpc":"2.0","id":"x","result":{}}"#,
+        );
+
+        assert!(matches!(updates.try_recv(), Ok(InspectorUpdate::Added(e)) if e.source_id == "a"));
+        assert!(
+            matches!(updates.try_recv(), Ok(InspectorUpdate::Completed(e)) if e.response.is_some())
+        );
+        assert!(updates.try_recv().is_err());
+
+        assert!(inspector.unsubscribe(subscription));
+        inspector.capture(
+            "a",
+            Direction::Outbound,
+            r#"{"jsonrpc":"2.0","method":"x"}"#,
+        );
+        assert!(updates.try_recv().is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::mcp::events::ExitInfo;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Per-process resource limits, part of `ProcessOptions`. Unset fields leave the
+/// limit inherited from Daan.
+///
+/// On Unix the limits are rlimits set in the child before `exec`, so they apply
+/// to every process of the server's tree individually rather than to the tree as
+/// a whole (that would need a cgroup, which a desktop app usually isn't delegated).
+/// On Windows only `max_lifetime_ms` is enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceLimits {
+    /// Address space (`RLIMIT_AS`). This counts reserved virtual memory, which
+    /// runtimes like Node.js and the JVM reserve generously, so leave headroom.
+    pub max_memory_mb: Option<u64>,
+    /// CPU time (`RLIMIT_CPU`); the process gets `SIGXCPU` once it is used up.
+    pub max_cpu_seconds: Option<u64>,
+    /// Open file descriptors (`RLIMIT_NOFILE`).
+    pub max_open_files: Option<u64>,
+    /// Processes (`RLIMIT_NPROC`). The kernel counts every process of the user,
+    /// not only the server's, so this only stops runaway forking.
+    pub max_processes: Option<u64>,
+    /// Wall-clock time a run may last before it is shut down like a stop request.
+    pub max_lifetime_ms: Option<u64>,
+}
+
+/// The limit a process ran into, reported in `ExitInfo::limit_exceeded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceLimit {
+    Memory,
+    CpuTime,
+    OpenFiles,
+    Processes,
+    Lifetime,
+}
+
+impl std::fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ResourceLimit::Memory => "Memory limit",
+            ResourceLimit::CpuTime => "CPU time limit",
+            ResourceLimit::OpenFiles => "Open files limit",
+            ResourceLimit::Processes => "Process limit",
+            ResourceLimit::Lifetime => "Lifetime limit",
+        })
+    }
+}
+
+// Only a CPU limit ends the process with a telltale signal; the other rlimits make
+// system calls fail, which a server reports (or not) in its own words
+const MEMORY_ERRORS: &[&str] = &[
+    "out of memory",
+    "cannot allocate memory",
+    "memoryerror",
+    "bad_alloc",
+    "allocation failed",
+];
+const OPEN_FILES_ERRORS: &[&str] = &["too many open files", "emfile"];
+// A process limit makes creating processes and threads fail with EAGAIN, which
+// non-blocking I/O also reports all the time, so EAGAIN only counts next to one of
+// the calls that create them
+const PROCESS_CREATION_CALLS: &[&str] = &["fork", "spawn", "pthread_create"];
+const EAGAIN_ERRORS: &[&str] = &["resource temporarily unavailable", "eagain"];
+// Runtimes' own words for a thread they could not create
+const THREAD_ERRORS: &[&str] = &[
+    "can't start new thread",
+    "failed to create new os thread",
+    "unable to create native thread",
+    "unable to create new native thread",
+];
+
+impl ResourceLimits {
+    pub fn lifetime(&self) -> Option<Duration> {
+        self.max_lifetime_ms.map(Duration::from_millis)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let limits = [
+            ("memory", self.max_memory_mb),
+            ("CPU time", self.max_cpu_seconds),
+            ("open files", self.max_open_files),
+            ("processes", self.max_processes),
+            ("lifetime", self.max_lifetime_ms),
+        ];
+        match limits.iter().find(|(_, value)| *value == Some(0)) {
+            Some((name, _)) => Err(format!("The {} limit must be greater than zero.", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the rlimits in the child between `fork` and `exec`.
+    #[cfg(unix)]
+    pub fn apply_to(&self, cmd: &mut tokio::process::Command) {
+        if self.max_memory_mb.is_none()
+            && self.max_cpu_seconds.is_none()
+            && self.max_open_files.is_none()
+            && self.max_processes.is_none()
+        {
+            return;
+        }
+        let limits = *self;
+        // SAFETY: the closure only calls getrlimit/setrlimit, which are
+        // async-signal-safe, and does not allocate
+        unsafe {
+            cmd.pre_exec(move || limits.set_rlimits());
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn apply_to(&self, _cmd: &mut tokio::process::Command) {}
+
+    #[cfg(unix)]
+    fn set_rlimits(&self) -> std::io::Result<()> {
+        if let Some(mb) = self.max_memory_mb {
+            set_rlimit(libc::RLIMIT_AS, mb.saturating_mul(1024 * 1024), 0)?;
+        }
+        if let Some(seconds) = self.max_cpu_seconds {
+            // SIGXCPU at the soft limit, SIGKILL a second later if it is handled
+            set_rlimit(libc::RLIMIT_CPU, seconds, 1)?;
+        }
+        if let Some(files) = self.max_open_files {
+            set_rlimit(libc::RLIMIT_NOFILE, files, 0)?;
+        }
+        if let Some(processes) = self.max_processes {
+            set_rlimit(libc::RLIMIT_NPROC, processes, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Works out which limit, if any, ended a run that exited on its own, i.e. not
+    /// through a stop request or the lifetime limit.
+    pub fn detect(&self, exit: &ExitInfo) -> Option<ResourceLimit> {
+        // A process handling or ignoring SIGXCPU gets SIGKILL at the hard limit
+        #[cfg(unix)]
+        if self.max_cpu_seconds.is_some()
+            && matches!(exit.signal, Some(libc::SIGXCPU | libc::SIGKILL))
+        {
+            return Some(ResourceLimit::CpuTime);
+        }
+        let stderr = exit.stderr_tail.join("\n").to_lowercase();
+        let mentions = |markers: &[&str]| markers.iter().any(|marker| stderr.contains(marker));
+        if self.max_memory_mb.is_some() && mentions(MEMORY_ERRORS) {
+            Some(ResourceLimit::Memory)
+        } else if self.max_open_files.is_some() && mentions(OPEN_FILES_ERRORS) {
+            Some(ResourceLimit::OpenFiles)
+        } else if self.max_processes.is_some() && stderr.lines().any(failed_to_create_process) {
+            Some(ResourceLimit::Processes)
+        } else {
+            None
+        }
+    }
+}
+
+// "fork: retry: Resource temporarily unavailable", "Error: spawn EAGAIN", but not
+// "Error: EAGAIN: resource temporarily unavailable, read"
+fn failed_to_create_process(line: &str) -> bool {
+    let mentions = |markers: &[&str]| markers.iter().any(|marker| line.contains(marker));
+    mentions(THREAD_ERRORS) || (mentions(PROCESS_CREATION_CALLS) && mentions(EAGAIN_ERRORS))
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+// Sets the soft limit to `value` and the hard limit `hard_extra` above it, never
+// above the current hard limit (raising it needs privileges)
+#[cfg(unix)]
+fn set_rlimit(resource: RlimitResource, value: u64, hard_extra: u64) -> std::io::Result<()> {
+    let mut current = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `current` is a valid rlimit to write to
+    if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let value = value as libc::rlim_t;
+    let hard = value.saturating_add(hard_extra as libc::rlim_t);
+    let limit = libc::rlimit {
+        rlim_cur: value.min(current.rlim_max),
+        rlim_max: hard.min(current.rlim_max),
+    };
+    // SAFETY: `limit` is a valid rlimit
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_limits_from_the_exit() {
+        let limits = ResourceLimits {
+            max_memory_mb: Some(512),
+            max_cpu_seconds: Some(10),
+            ..Default::default()
+        };
+        let oom = ExitInfo {
+            code: Some(134),
+            stderr_tail: vec!["FATAL ERROR: JavaScript heap out of memory".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(limits.detect(&oom), Some(ResourceLimit::Memory));
+        #[cfg(unix)]
+        for signal in [libc::SIGXCPU, libc::SIGKILL] {
+            let killed = ExitInfo {
+                signal: Some(signal),
+                ..Default::default()
+            };
+            assert_eq!(limits.detect(&killed), Some(ResourceLimit::CpuTime));
+            assert_eq!(ResourceLimits::default().detect(&killed), None);
+        }
+
+        // Without the limit set, the same output is just a crash
+        assert_eq!(ResourceLimits::default().detect(&oom), None);
+        let files = ExitInfo {
+            stderr_tail: vec!["Error: EMFILE: too many open files".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(limits.detect(&files), None);
+    }
+
+    #[test]
+    fn detects_the_process_limit_only_from_failed_process_creation() {
+        let limits = ResourceLimits {
+            max_processes: Some(32),
+            ..Default::default()
+        };
+        let exit_with = |line: &str| ExitInfo {
+            code: Some(1),
+            stderr_tail: vec!["Starting server".to_string(), line.to_string()],
+            ..Default::default()
+        };
+        for line in [
+            "sh: fork: retry: Resource temporarily unavailable",
+            "Error: spawn /usr/bin/git EAGAIN",
+            "RuntimeError: can't start new thread",
+            "runtime: failed to create new OS thread (have 5 already; errno=11)",
+        ] {
+            assert_eq!(
+                limits.detect(&exit_with(line)),
+                Some(ResourceLimit::Processes),
+                "{}",
+                line
+            );
+        }
+        // Node reports non-blocking stdio like this, whatever the limits
+        let stdio = exit_with("Error: EAGAIN: resource temporarily unavailable, read");
+        assert_eq!(limits.detect(&stdio), None);
+    }
+
+    #[test]
+    fn rejects_zero_limits() {
+        let limits = ResourceLimits {
+            max_open_files: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.validate(),
+            Err("The open files limit must be greater than zero.".to_string())
+        );
+        assert!(ResourceLimits::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::mcp::control::now_millis;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Lines kept per process; older lines are dropped first.
+pub const PROCESS_LOG_CAPACITY: usize = 500;
+
+/// How long the log of an exited process stays retrievable.
+pub const EXITED_LOG_RETENTION: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// Lifecycle notes written by the backend (exit status, restarts, ...).
+    System,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+/// Bounded ring buffer of recent output lines, shared by a process's reader tasks.
+#[derive(Clone)]
+pub struct ProcessLog {
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+    // Stdout lines that were not JSON-RPC and only went to the log
+    ignored_stdout: Arc<AtomicU64>,
+}
+
+impl Default for ProcessLog {
+    fn default() -> Self {
+        ProcessLog {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(PROCESS_LOG_CAPACITY))),
+            ignored_stdout: Arc::default(),
+        }
+    }
+}
+
+impl ProcessLog {
+    /// Logs a stdout line that is not a protocol message and counts it. Returns
+    /// the number of such lines so far, including this one.
+    pub fn push_ignored_stdout(&self, line: impl Into<String>) -> u64 {
+        self.push(LogStream::Stdout, line);
+        self.ignored_stdout.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn ignored_stdout_lines(&self) -> u64 {
+        self.ignored_stdout.load(Ordering::Relaxed)
+    }
+
+    pub fn push(&self, stream: LogStream, line: impl Into<String>) {
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        if lines.len() == PROCESS_LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine {
+            timestamp: now_millis(),
+            stream,
+            line: line.into(),
+        });
+    }
+
+    /// Returns the most recent `limit` lines of `stream`, oldest first.
+    pub fn tail_of(&self, stream: LogStream, limit: usize) -> Vec<String> {
+        let Ok(lines) = self.lines.lock() else {
+            return Vec::new();
+        };
+        let mut tail: Vec<String> = lines
+            .iter()
+            .rev()
+            .filter(|line| line.stream == stream)
+            .take(limit)
+            .map(|line| line.line.clone())
+            .collect();
+        tail.reverse();
+        tail
+    }
+
+    /// Returns the most recent `limit` lines (all of them if `None`), oldest first.
+    pub fn tail(&self, limit: Option<usize>) -> Vec<LogLine> {
+        let Ok(lines) = self.lines.lock() else {
+            return Vec::new();
+        };
+        let skip = limit.map_or(0, |limit| lines.len().saturating_sub(limit));
+        lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Log of a process that has left the registry, kept around for diagnostics.
+pub struct ExitedLog {
+    pub log: ProcessLog,
+    pub exited_at: tokio::time::Instant,
+}
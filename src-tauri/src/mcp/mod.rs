@@ -1,2 +1,19 @@
-pub(crate) mod cmd;
-pub(crate) mod control;
+pub mod client;
+pub mod cmd;
+pub mod control;
+pub mod events;
+pub mod framing;
+pub mod http;
+pub mod import;
+pub mod inspector;
+pub mod limits;
+pub mod logs;
+pub mod recorder;
+pub mod remote;
+pub mod resolve;
+pub mod runtimes;
+pub mod sandbox;
+pub mod sse;
+pub mod supervisor;
+pub mod websocket;
+pub mod writer;
@@ -0,0 +1,155 @@
+use crate::mcp::control::now_millis;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Which way a recorded message went, seen from Daan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    /// Written to the server's stdin.
+    Outbound,
+    /// Read from the server's stdout.
+    Inbound,
+}
+
+/// One line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedMessage {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub direction: Direction,
+    /// The message as JSON, or as a string if it was not valid JSON.
+    pub message: Value,
+}
+
+/// Appends every JSON-RPC message exchanged with a process to a JSONL file, one
+/// `RecordedMessage` per line. Shared by the process's writer and stdout reader,
+/// and kept across restarts so one file covers the whole process.
+#[derive(Clone)]
+pub struct TrafficRecorder {
+    path: PathBuf,
+    file: Arc<Mutex<LineWriter<File>>>,
+}
+
+impl TrafficRecorder {
+    /// Creates `<dir>/<UTC timestamp>-<process id>.jsonl`, creating `dir` if needed.
+    pub fn create(dir: &Path, process_id: &str) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let short_id: String = process_id.chars().take(8).collect();
+        let path = dir.join(format!(
+            "{}-{}.jsonl",
+            utc_timestamp(now_millis()),
+            short_id
+        ));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        println!(
+            "Recording traffic of process {} to {}",
+            process_id,
+            path.display()
+        );
+        Ok(Self {
+            path,
+            file: Arc::new(Mutex::new(LineWriter::new(file))),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `message`. Failures are logged and otherwise ignored, so a full
+    /// disk never gets in the way of the process.
+    pub fn record(&self, direction: Direction, message: &str) {
+        let entry = RecordedMessage {
+            timestamp: now_millis(),
+            direction,
+            message: serde_json::from_str(message)
+                .unwrap_or_else(|_| Value::String(message.to_string())),
+        };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        // LineWriter flushes on the newline, so the trace survives a crash
+        if let Err(e) = writeln!(file, "{}", line) {
+            eprintln!("Failed to record traffic to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Reads a recording back, skipping lines that are not recorded messages.
+pub fn read_recording(path: &Path) -> std::io::Result<Vec<RecordedMessage>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// Formats epoch milliseconds as `YYYYMMDDTHHMMSSZ`, so recordings sort by start time
+fn utc_timestamp(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(utc_timestamp(0), "19700101T000000Z");
+        assert_eq!(utc_timestamp(951_782_400_000), "20000229T000000Z");
+        assert_eq!(utc_timestamp(1_792_152_123_000), "20261016T120203Z");
+    }
+
+    #[test]
+    fn records_messages_as_jsonl() {
+        let dir = std::env::temp_dir().join(format!("daan-recorder-{}", uuid::Uuid::new_v4()));
+        let recorder = TrafficRecorder::create(&dir, "0123456789abcdef").unwrap();
+        recorder.record(
+            Direction::Outbound,
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+        );
+        recorder.record(Direction::Inbound, "not json");
+
+        let recorded = read_recording(recorder.path()).unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].direction, Direction::Outbound);
+        assert_eq!(recorded[0].message["method"], "ping");
+        assert_eq!(recorded[1].message, Value::String("not json".to_string()));
+        assert!(recorder
+            .path()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with("-01234567.jsonl"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::mcp::control::now_millis;
+use crate::mcp::events::{ExitInfo, ProcessEvent, ProcessEvents};
+use crate::mcp::http::{run_streamable_http, HttpEndpoint};
+use crate::mcp::inspector::{TrafficInspector, TrafficTap};
+use crate::mcp::sse::run_legacy_sse;
+use crate::mcp::supervisor::{RestartPolicy, RestartingEvent};
+use crate::mcp::websocket::{run_websocket, WebSocketConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LockResult, Mutex, MutexGuard};
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+/// Messages that may wait for a transport task before senders are held back.
+const OUTGOING_QUEUE_CAPACITY: usize = 64;
+
+/// Protocol spoken with a remote MCP server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RemoteTransport {
+    StreamableHttp,
+    /// The HTTP+SSE transport used before Streamable HTTP.
+    Sse,
+    #[serde(rename = "websocket")]
+    WebSocket,
+}
+
+/// Connection settings sent by the frontend along with the URL.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteOptions {
+    /// Extra headers sent with every request, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// WebSocket only: interval between keepalive pings.
+    #[serde(default)]
+    pub ping_interval_ms: Option<u64>,
+    /// WebSocket only: when and how often to reconnect after the connection drops.
+    /// Defaults to always reconnecting.
+    #[serde(default)]
+    pub reconnect: Option<RestartPolicy>,
+}
+
+/// What a transport task reports back; relayed to the connection's `ProcessEvents`
+/// like the events of a stdio process (reconnects as restarts).
+#[derive(Debug)]
+pub enum TransportEvent {
+    Message(String),
+    Error(String),
+    /// The connection dropped and will be re-established after the delay.
+    Reconnecting(RestartingEvent),
+    /// Connected again; carries the number of reconnects so far.
+    Reconnected(u32),
+    Closed(String),
+}
+
+/// A message queued for a transport task, with a channel for the send result.
+pub struct OutgoingMessage {
+    pub message: String,
+    pub reply: oneshot::Sender<Result<(), String>>,
+}
+
+/// The ends of a remote connection owned by its transport task.
+pub struct TransportChannels {
+    pub outgoing: mpsc::Receiver<OutgoingMessage>,
+    pub stop: oneshot::Receiver<()>,
+    pub events: mpsc::Sender<TransportEvent>,
+}
+
+/// Snapshot of a `RemoteConnection` returned by `list_remote_connections`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteConnectionInfo {
+    pub id: String,
+    pub transport: RemoteTransport,
+    pub url: String,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+}
+
+pub struct RemoteConnection {
+    pub transport: RemoteTransport,
+    pub url: String,
+    pub started_at: u64,
+    outgoing: mpsc::Sender<OutgoingMessage>,
+    stop_signal: Option<oneshot::Sender<()>>,
+    tap: TrafficTap,
+}
+
+impl RemoteConnection {
+    pub fn outgoing(&self) -> mpsc::Sender<OutgoingMessage> {
+        self.outgoing.clone()
+    }
+
+    pub fn tap(&self) -> TrafficTap {
+        self.tap.clone()
+    }
+
+    /// Asks the transport task to close the connection. Returns `false` if a stop
+    /// was already requested.
+    pub fn request_stop(&mut self) -> bool {
+        match self.stop_signal.take() {
+            Some(signal) => {
+                let _ = signal.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn info(&self, id: &str) -> RemoteConnectionInfo {
+        RemoteConnectionInfo {
+            id: id.to_string(),
+            transport: self.transport,
+            url: self.url.clone(),
+            started_at: self.started_at,
+        }
+    }
+}
+
+/// Remote connections, kept next to the `ProcessRegistry` of stdio processes.
+#[derive(Default, Clone)]
+pub struct ConnectionRegistry {
+    connections: Arc<Mutex<HashMap<String, RemoteConnection>>>,
+    // Captures the traffic of every connection for the inspector
+    inspector: TrafficInspector,
+}
+
+impl ConnectionRegistry {
+    /// Captures into `inspector`, shared with the `ProcessRegistry`.
+    pub fn with_inspector(mut self, inspector: TrafficInspector) -> Self {
+        self.inspector = inspector;
+        self
+    }
+
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, HashMap<String, RemoteConnection>>> {
+        self.connections.lock()
+    }
+}
+
+/// Opens a connection to a remote server and returns its id. Messages, errors and
+/// reconnects are sent to `events` from the start, and `Closed` once the
+/// connection is gone.
+pub fn start_connection(
+    transport: RemoteTransport,
+    url: &str,
+    options: &RemoteOptions,
+    events: ProcessEvents,
+    registry: &ConnectionRegistry,
+) -> Result<String, String> {
+    let (outgoing_sender, outgoing) = mpsc::channel(OUTGOING_QUEUE_CAPACITY);
+    let (stop_signal, stop) = oneshot::channel();
+    let (event_sender, event_receiver) = mpsc::channel(OUTGOING_QUEUE_CAPACITY);
+    let channels = TransportChannels {
+        outgoing,
+        stop,
+        events: event_sender,
+    };
+
+    match transport {
+        RemoteTransport::StreamableHttp => {
+            let config = HttpEndpoint::new(url, &options.headers)?;
+            tokio::spawn(run_streamable_http(config, channels));
+        }
+        RemoteTransport::Sse => {
+            let endpoint = HttpEndpoint::new(url, &options.headers)?;
+            tokio::spawn(run_legacy_sse(endpoint, channels));
+        }
+        RemoteTransport::WebSocket => {
+            let config = WebSocketConfig::new(url, options)?;
+            tokio::spawn(run_websocket(config, channels));
+        }
+    }
+
+    let connection_id = Uuid::new_v4().to_string();
+    let tap = TrafficTap::new(connection_id.clone()).with_inspector(registry.inspector.clone());
+    registry
+        .lock()
+        .map_err(|_| "Mutex poisoned".to_string())?
+        .insert(
+            connection_id.clone(),
+            RemoteConnection {
+                transport,
+                url: url.to_string(),
+                started_at: now_millis(),
+                outgoing: outgoing_sender,
+                stop_signal: Some(stop_signal),
+                tap: tap.clone(),
+            },
+        );
+    println!(
+        "Opened {:?} connection {} to {}.",
+        transport, connection_id, url
+    );
+
+    tokio::spawn(forward_events(
+        connection_id.clone(),
+        event_receiver,
+        tap,
+        events,
+        registry.clone(),
+    ));
+    Ok(connection_id)
+}
+
+// Relays transport events to the frontend and drops the registry entry once the
+// transport task is done.
+async fn forward_events(
+    connection_id: String,
+    mut receiver: mpsc::Receiver<TransportEvent>,
+    tap: TrafficTap,
+    events: ProcessEvents,
+    registry: ConnectionRegistry,
+) {
+    let started_at = now_millis();
+    let mut close_reason = "Connection closed".to_string();
+    while let Some(event) = receiver.recv().await {
+        match event {
+            TransportEvent::Message(message) => {
+                tap.inbound(&message);
+                events.send(ProcessEvent::Message(message));
+            }
+            TransportEvent::Error(error) => {
+                eprintln!("Error on connection {}: {}", connection_id, error);
+                events.send(ProcessEvent::Error(error));
+            }
+            TransportEvent::Reconnecting(event) => events.send(ProcessEvent::Restarting(event)),
+            TransportEvent::Reconnected(restart_count) => {
+                events.send(ProcessEvent::Restarted { restart_count })
+            }
+            TransportEvent::Closed(reason) => {
+                close_reason = reason;
+                break;
+            }
+        }
+    }
+
+    // A connection whose stop signal was used is closing on request
+    let stop_requested = registry
+        .lock()
+        .ok()
+        .and_then(|mut guard| guard.remove(&connection_id))
+        .is_some_and(|connection| connection.stop_signal.is_none());
+    println!("Connection {} closed: {}", connection_id, close_reason);
+    events.send(ProcessEvent::Closed(ExitInfo {
+        stop_requested,
+        uptime_ms: now_millis().saturating_sub(started_at),
+        reason: Some(close_reason),
+        ..Default::default()
+    }));
+}
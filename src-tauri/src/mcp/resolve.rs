@@ -0,0 +1,325 @@
+use crate::mcp::control::{EnvPolicy, ProcessOptions};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// How long the login shell may take to print its PATH before it is given up on.
+const LOGIN_SHELL_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Brackets the PATH in the login shell's output, which may also contain a motd
+const PATH_MARKER: &str = "__DAAN_PATH__";
+
+/// A command resolved to the program that is actually executed.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedCommand {
+    /// Absolute path of the executable.
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// PATH for the child, covering the directories searched to find the program.
+    /// `None` if the child should keep the PATH it gets from its environment.
+    pub search_path: Option<OsString>,
+}
+
+/// Finds the executable for `command` without involving a shell, unless
+/// `options.run_in_shell` asks for one.
+///
+/// A command containing a path separator is taken relative to `options.cwd`.
+/// Anything else is searched in the child's PATH, then in the PATH of the user's
+/// login shell (apps launched from a desktop environment miss what shell profiles
+/// add), then in nvm, asdf and pyenv shims.
+pub fn resolve_command(
+    command: &str,
+    args: &[String],
+    options: &ProcessOptions,
+) -> Result<ResolvedCommand, String> {
+    let command = command.trim();
+    if command.is_empty() {
+        return Err("Command is empty.".to_string());
+    }
+    let search_dirs = search_dirs(options);
+    let (program, args) = if options.run_in_shell {
+        shell_invocation(command, args)
+    } else {
+        (command.to_string(), args.to_vec())
+    };
+    let program = find_program(&program, options, &search_dirs)?;
+
+    // Let scripts like `#!/usr/bin/env node` find their interpreter in the same places
+    let search_path = (options.env_policy == EnvPolicy::Inherit
+        && !options.env.contains_key("PATH"))
+    .then(|| std::env::join_paths(&search_dirs).ok())
+    .flatten();
+    Ok(ResolvedCommand {
+        program,
+        args,
+        search_path,
+    })
+}
+
+// The platform shell interprets `command` as written, with `args` quoted after it
+fn shell_invocation(command: &str, args: &[String]) -> (String, Vec<String>) {
+    if cfg!(target_os = "windows") {
+        let mut shell_args = vec!["/c".to_string(), command.to_string()];
+        shell_args.extend(args.iter().cloned());
+        ("cmd.exe".to_string(), shell_args)
+    } else {
+        let mut script = command.to_string();
+        if !args.is_empty() {
+            script.push(' ');
+            script.push_str(&shell_words::join(args));
+        }
+        ("sh".to_string(), vec!["-c".to_string(), script])
+    }
+}
+
+fn find_program(
+    program: &str,
+    options: &ProcessOptions,
+    search_dirs: &[PathBuf],
+) -> Result<PathBuf, String> {
+    let has_separator = program.contains('/') || (cfg!(windows) && program.contains('\\'));
+    if has_separator {
+        let path = Path::new(program);
+        let path = match options.cwd.as_deref().filter(|dir| !dir.trim().is_empty()) {
+            Some(cwd) if path.is_relative() => Path::new(cwd).join(path),
+            _ => std::path::absolute(path).map_err(|e| e.to_string())?,
+        };
+        return executable(&path)
+            .ok_or_else(|| format!("Command '{}' does not exist or is not executable.", program));
+    }
+    search_dirs
+        .iter()
+        .find_map(|dir| executable(&dir.join(program)))
+        .ok_or_else(|| {
+            format!(
+                "Command '{}' was not found in PATH, the login shell's PATH or version manager shims. \
+                 Check the spelling, use an absolute path, or turn on Run in Shell.",
+                program
+            )
+        })
+}
+
+// Returns the path to run if `path` is an executable file, trying PATHEXT on Windows
+fn executable(path: &Path) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = std::fs::metadata(path).ok()?;
+        (metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+            .then(|| path.to_path_buf())
+    }
+    #[cfg(windows)]
+    {
+        if path.extension().is_some() && path.is_file() {
+            return Some(path.to_path_buf());
+        }
+        let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+        extensions
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| {
+                let mut candidate = path.as_os_str().to_owned();
+                candidate.push(ext);
+                PathBuf::from(candidate)
+            })
+            .find(|candidate| candidate.is_file())
+    }
+}
+
+// The child's PATH (or Daan's), then the login shell's, then shims; without duplicates
+fn search_dirs(options: &ProcessOptions) -> Vec<PathBuf> {
+    let base = match options.env.get("PATH") {
+        Some(path) => Some(OsString::from(path)),
+        None => std::env::var_os("PATH"),
+    };
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    let candidates = base
+        .iter()
+        .flat_map(std::env::split_paths)
+        .chain(
+            login_shell_path()
+                .into_iter()
+                .flat_map(std::env::split_paths),
+        )
+        .chain(version_manager_dirs());
+    for dir in candidates {
+        if !dir.as_os_str().is_empty() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// PATH as set up by the user's login shell, read once per run of Daan.
+pub fn login_shell_path() -> Option<&'static OsString> {
+    static LOGIN_PATH: OnceLock<Option<OsString>> = OnceLock::new();
+    LOGIN_PATH.get_or_init(read_login_shell_path).as_ref()
+}
+
+#[cfg(unix)]
+fn read_login_shell_path() -> Option<OsString> {
+    let shell = std::env::var_os("SHELL")?;
+    let mut child = std::process::Command::new(&shell)
+        .args([
+            "-l",
+            "-c",
+            &format!("printf '{0}%s{0}' \"$PATH\"", PATH_MARKER),
+        ])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .ok()?;
+    // A profile waiting for input must not hold up every server start
+    let deadline = Instant::now() + LOGIN_SHELL_TIMEOUT;
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() >= deadline {
+            eprintln!("Login shell {:?} did not print its PATH in time.", shell);
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let mut output = String::new();
+    std::io::Read::read_to_string(child.stdout.as_mut()?, &mut output).ok()?;
+    parse_marked_path(&output).map(OsString::from)
+}
+
+// Only Unix desktop launches lose the shell's PATH
+#[cfg(not(unix))]
+fn read_login_shell_path() -> Option<OsString> {
+    None
+}
+
+fn parse_marked_path(output: &str) -> Option<&str> {
+    let start = output.find(PATH_MARKER)? + PATH_MARKER.len();
+    let len = output[start..].find(PATH_MARKER)?;
+    Some(&output[start..start + len]).filter(|path| !path.is_empty())
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(var).map(PathBuf::from)
+}
+
+// Shim directories of asdf and pyenv, and the bin directory of nvm's default Node
+fn version_manager_dirs() -> Vec<PathBuf> {
+    let home = home_dir();
+    let root = |var: &str, default: &str| {
+        std::env::var_os(var)
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(default)))
+    };
+    let mut dirs = Vec::new();
+    if let Some(asdf) = root("ASDF_DATA_DIR", ".asdf") {
+        dirs.push(asdf.join("shims"));
+    }
+    if let Some(pyenv) = root("PYENV_ROOT", ".pyenv") {
+        dirs.push(pyenv.join("shims"));
+    }
+    if let Some(nvm) = root("NVM_DIR", ".nvm") {
+        if let Some(bin) = nvm_default_bin(&nvm) {
+            dirs.push(bin);
+        }
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+fn nvm_default_bin(nvm_dir: &Path) -> Option<PathBuf> {
+    let versions_dir = nvm_dir.join("versions").join("node");
+    let installed: Vec<String> = std::fs::read_dir(&versions_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    let alias = std::fs::read_to_string(nvm_dir.join("alias").join("default")).unwrap_or_default();
+    let version = pick_nvm_version(alias.trim(), &installed)?;
+    Some(versions_dir.join(version).join("bin"))
+}
+
+// The newest installed version matching the alias ("20", "v20.1", ...); aliases
+// like "node" or "lts/*" fall back to the newest version overall
+fn pick_nvm_version<'a>(alias: &str, installed: &'a [String]) -> Option<&'a String> {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+    let wanted = parse(alias).unwrap_or_default();
+    installed
+        .iter()
+        .filter_map(|version| Some((parse(version)?, version)))
+        .filter(|(parts, _)| parts.starts_with(&wanted))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_newest_matching_nvm_version() {
+        let installed: Vec<String> = ["v18.20.4", "v20.9.0", "v20.11.1", "v22.1.0"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            pick_nvm_version("20", &installed).map(String::as_str),
+            Some("v20.11.1")
+        );
+        assert_eq!(
+            pick_nvm_version("v18.20.4", &installed).map(String::as_str),
+            Some("v18.20.4")
+        );
+        assert_eq!(
+            pick_nvm_version("lts/*", &installed).map(String::as_str),
+            Some("v22.1.0")
+        );
+        assert_eq!(pick_nvm_version("16", &installed), None);
+    }
+
+    #[test]
+    fn extracts_the_path_from_login_shell_output() {
+        let output = "Welcome!\n__DAAN_PATH__/usr/local/bin:/usr/bin__DAAN_PATH__";
+        assert_eq!(parse_marked_path(output), Some("/usr/local/bin:/usr/bin"));
+        assert_eq!(parse_marked_path("__DAAN_PATH____DAAN_PATH__"), None);
+        assert_eq!(parse_marked_path("no marker"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_commands_in_path_and_relative_to_cwd() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("daan-resolve-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        let server = dir.join("bin").join("daan-test-server");
+        std::fs::write(&server, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&server, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(dir.join("bin").join("not-executable"), "").unwrap();
+
+        let mut options = ProcessOptions::default();
+        options.env.insert(
+            "PATH".to_string(),
+            dir.join("bin").to_string_lossy().into_owned(),
+        );
+        let resolved = resolve_command("daan-test-server", &[], &options).unwrap();
+        assert_eq!(resolved.program, server);
+        // The configured PATH is left alone
+        assert_eq!(resolved.search_path, None);
+
+        options.cwd = Some(dir.to_string_lossy().into_owned());
+        let resolved = resolve_command("bin/daan-test-server", &[], &options).unwrap();
+        assert_eq!(resolved.program, server);
+
+        assert!(resolve_command("not-executable", &[], &options).is_err());
+        assert!(resolve_command("daan-no-such-command", &[], &options)
+            .unwrap_err()
+            .contains("was not found"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
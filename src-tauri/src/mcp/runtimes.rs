@@ -0,0 +1,203 @@
+use crate::mcp::control::ProcessOptions;
+use crate::mcp::resolve::{resolve_command, ResolvedCommand};
+use serde::Serialize;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// How long a runtime may take to print its version.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runtimes MCP servers are commonly launched with, and the argument that
+/// makes each print its version.
+const RUNTIMES: &[(&str, &str)] = &[
+    ("node", "--version"),
+    ("npx", "--version"),
+    ("python3", "--version"),
+    ("python", "--version"),
+    ("uv", "--version"),
+    ("uvx", "--version"),
+    ("deno", "--version"),
+    ("bun", "--version"),
+    ("docker", "--version"),
+];
+
+/// A runtime as found by `detect_runtimes`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeInfo {
+    /// Command name, e.g. `npx`.
+    pub name: String,
+    /// Executable the command resolves to; `None` if it was not found.
+    pub path: Option<String>,
+    pub version: Option<String>,
+    /// Why the runtime is unusable: not found, or it failed to report a version.
+    pub error: Option<String>,
+}
+
+impl RuntimeInfo {
+    pub fn is_available(&self) -> bool {
+        self.path.is_some() && self.error.is_none()
+    }
+}
+
+/// Looks up every known runtime the way a server command with `options` would be
+/// resolved, and asks each one found for its version.
+pub async fn detect_runtimes(options: &ProcessOptions) -> Vec<RuntimeInfo> {
+    // Runtimes are looked up by name, never as shell code
+    let options = ProcessOptions {
+        run_in_shell: false,
+        ..options.clone()
+    };
+    let checks = RUNTIMES
+        .iter()
+        .map(|(name, version_arg)| detect_runtime(name, version_arg, &options));
+    futures::future::join_all(checks).await
+}
+
+async fn detect_runtime(name: &str, version_arg: &str, options: &ProcessOptions) -> RuntimeInfo {
+    let mut info = RuntimeInfo {
+        name: name.to_string(),
+        path: None,
+        version: None,
+        error: None,
+    };
+    let resolving = {
+        let (name, args, options) = (
+            name.to_string(),
+            vec![version_arg.to_string()],
+            options.clone(),
+        );
+        tokio::task::spawn_blocking(move || resolve_command(&name, &args, &options))
+    };
+    let resolved = match resolving.await.map_err(|e| e.to_string()) {
+        Ok(Ok(resolved)) => resolved,
+        Ok(Err(e)) | Err(e) => {
+            info.error = Some(e);
+            return info;
+        }
+    };
+    info.path = Some(resolved.program.to_string_lossy().into_owned());
+    match read_version(&resolved, options).await {
+        Ok(version) => info.version = Some(version),
+        Err(e) => info.error = Some(e),
+    }
+    info
+}
+
+// Runs the resolved version command with the server's environment
+async fn read_version(
+    resolved: &ResolvedCommand,
+    options: &ProcessOptions,
+) -> Result<String, String> {
+    let mut cmd = tokio::process::Command::new(&resolved.program);
+    cmd.args(&resolved.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    options.apply_to(&mut cmd);
+    if let Some(path) = &resolved.search_path {
+        cmd.env("PATH", path);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = tokio::time::timeout(VERSION_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| format!("No version reported within {:?}.", VERSION_TIMEOUT))?
+        .map_err(|e| format!("Failed to run: {}", e))?;
+    // Python 2 prints its version on stderr
+    let text = [&output.stdout[..], &output.stderr[..]].concat();
+    let text = String::from_utf8_lossy(&text);
+    match parse_version(&text) {
+        Some(version) if output.status.success() => Ok(version.to_string()),
+        _ => {
+            let detail = text.lines().find(|line| !line.trim().is_empty());
+            Err(match detail {
+                Some(line) => format!("Version check failed ({}): {}", output.status, line.trim()),
+                None => format!("Version check failed ({}).", output.status),
+            })
+        }
+    }
+}
+
+// The first dotted version number in the output: "v20.11.1" -> "20.11.1",
+// "Docker version 27.0.3, build 7d4bcd8" -> "27.0.3"
+fn parse_version(output: &str) -> Option<&str> {
+    output
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .map(|word| word.strip_prefix('v').unwrap_or(word))
+        .find(|word| {
+            word.starts_with(|c: char| c.is_ascii_digit())
+                && word.contains('.')
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_output() {
+        assert_eq!(parse_version("v20.11.1\n"), Some("20.11.1"));
+        assert_eq!(parse_version("Python 3.12.1"), Some("3.12.1"));
+        assert_eq!(
+            parse_version("uv 0.4.18 (7b55e9790 2024-10-01)"),
+            Some("0.4.18")
+        );
+        assert_eq!(
+            parse_version("Docker version 27.0.3, build 7d4bcd8"),
+            Some("27.0.3")
+        );
+        assert_eq!(
+            parse_version("deno 1.46.3 (stable, release, x86_64-unknown-linux-gnu)\nv8 12.9"),
+            Some("1.46.3")
+        );
+        assert_eq!(parse_version("command not found"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn detects_runtimes_in_the_configured_path() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("daan-runtimes-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let scripts = [
+            ("node", "echo v20.11.1"),
+            ("uv", "echo 'error: broken install' >&2; exit 2"),
+        ];
+        for (name, body) in scripts {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let mut options = ProcessOptions::default();
+        options
+            .env
+            .insert("PATH".to_string(), dir.to_string_lossy().into_owned());
+        let runtimes = detect_runtimes(&options).await;
+        let find = |name: &str| runtimes.iter().find(|info| info.name == name).unwrap();
+
+        let node = find("node");
+        assert!(node.is_available());
+        assert_eq!(node.version.as_deref(), Some("20.11.1"));
+        assert_eq!(
+            node.path,
+            Some(dir.join("node").to_string_lossy().into_owned())
+        );
+
+        let uv = find("uv");
+        assert!(!uv.is_available());
+        assert!(uv.error.as_deref().unwrap().contains("broken install"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
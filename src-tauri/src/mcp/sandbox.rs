@@ -0,0 +1,652 @@
+use serde::{Deserialize, Serialize};
+
+/// Opt-in confinement for untrusted stdio servers, part of `ProcessOptions`.
+///
+/// On Linux the child enters it between `fork` and `exec`: a user namespace (with
+/// a mount namespace for the private /tmp and a network namespace to cut off the
+/// network), a Landlock ruleset making the filesystem read-only outside
+/// `writable_paths`, and a seccomp filter refusing system calls an MCP server has
+/// no business making. Other platforms refuse to start a sandboxed server rather
+/// than silently running it unconfined.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SandboxProfile {
+    /// Absolute paths the server may write to, recursively for directories.
+    pub writable_paths: Vec<String>,
+    /// Run in an empty network namespace: no network at all, not even loopback.
+    pub deny_network: bool,
+    /// Mount an empty, writable tmpfs over /tmp, gone when the server exits.
+    pub private_tmp: bool,
+}
+
+impl Default for SandboxProfile {
+    fn default() -> Self {
+        Self {
+            writable_paths: Vec::new(),
+            deny_network: false,
+            private_tmp: true,
+        }
+    }
+}
+
+// Writable inside any sandbox; plenty of programs send output there
+#[cfg(target_os = "linux")]
+const ALWAYS_WRITABLE: &[&str] = &["/dev/null"];
+
+impl SandboxProfile {
+    /// Checks the paths and that this system can enforce the profile.
+    pub fn validate(&self) -> Result<(), String> {
+        for path in &self.writable_paths {
+            if !std::path::Path::new(path).is_absolute() || path.contains('\0') {
+                return Err(format!(
+                    "Sandbox writable path '{}' must be absolute.",
+                    path
+                ));
+            }
+            if !std::path::Path::new(path).exists() {
+                return Err(format!("Sandbox writable path '{}' does not exist.", path));
+            }
+            if self.private_tmp && std::path::Path::new(path).starts_with("/tmp") {
+                return Err(format!(
+                    "Sandbox writable path '{}' is hidden by the private /tmp.",
+                    path
+                ));
+            }
+        }
+        platform::check_support(self)
+    }
+
+    /// Makes the child enter the sandbox before `exec`. Call `validate` first.
+    pub fn apply_to(&self, cmd: &mut tokio::process::Command) -> std::io::Result<()> {
+        platform::apply_to(self, cmd)
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod platform {
+    use super::{SandboxProfile, ALWAYS_WRITABLE};
+    use std::ffi::CString;
+    use std::io;
+    use std::sync::OnceLock;
+
+    // Landlock ABI (linux/landlock.h); libc does not define it yet
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    // Rights that apply to files as opposed to directories
+    const ACCESS_FS_FILE: u64 = ACCESS_FS_WRITE_FILE | ACCESS_FS_TRUNCATE;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: libc::c_int,
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    // Offsets into struct seccomp_data
+    const SECCOMP_DATA_NR: u32 = 0;
+    const SECCOMP_DATA_ARCH: u32 = 4;
+    // Low half of the first argument, on these little-endian architectures
+    const SECCOMP_DATA_ARG0: u32 = 16;
+
+    // clone flags creating namespaces, which a user namespace would make effective
+    const NAMESPACE_FLAGS: libc::c_int = libc::CLONE_NEWUSER
+        | libc::CLONE_NEWNS
+        | libc::CLONE_NEWNET
+        | libc::CLONE_NEWPID
+        | libc::CLONE_NEWIPC
+        | libc::CLONE_NEWUTS
+        | libc::CLONE_NEWCGROUP;
+
+    // Debugging, kernel modules and keyrings, and the namespace and mount calls
+    // that could undo the sandbox
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_kexec_load,
+        libc::SYS_kexec_file_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_reboot,
+        libc::SYS_setns,
+        libc::SYS_unshare,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_io_uring_setup,
+    ];
+
+    pub fn check_support(profile: &SandboxProfile) -> Result<(), String> {
+        match landlock_abi() {
+            Ok(abi) if abi >= 1 => {}
+            _ => return Err("The sandbox needs Landlock (Linux 5.13 or later), which is not enabled on this system.".to_string()),
+        }
+        if profile.private_tmp || profile.deny_network {
+            user_namespaces_supported().clone()?;
+        }
+        Ok(())
+    }
+
+    // Hosts can forbid unprivileged user namespaces (kernel.unprivileged_userns_clone,
+    // AppArmor's userns restriction, container seccomp profiles), which would only
+    // show as a failed spawn. A forked child tries once what `enter` does.
+    fn user_namespaces_supported() -> &'static Result<(), String> {
+        static SUPPORTED: OnceLock<Result<(), String>> = OnceLock::new();
+        SUPPORTED.get_or_init(|| {
+            let probe = Prepared::for_namespaces(libc::CLONE_NEWNS, true);
+            // SAFETY: the child only makes async-signal-safe calls on prepared data
+            // and leaves through _exit
+            let pid = unsafe { libc::fork() };
+            if pid == 0 {
+                let code = match probe.enter_namespaces() {
+                    Ok(()) => 0,
+                    Err(e) => e.raw_os_error().unwrap_or(libc::EPERM),
+                };
+                unsafe { libc::_exit(code) };
+            }
+            if pid < 0 {
+                return Err(format!(
+                    "Could not check for user namespace support: {}",
+                    io::Error::last_os_error()
+                ));
+            }
+            let mut status = 0;
+            // SAFETY: waits for the child forked above
+            if unsafe { libc::waitpid(pid, &mut status, 0) } < 0 {
+                return Err(format!(
+                    "Could not check for user namespace support: {}",
+                    io::Error::last_os_error()
+                ));
+            }
+            match (libc::WIFEXITED(status), libc::WEXITSTATUS(status)) {
+                (true, 0) => Ok(()),
+                (true, errno) => Err(format!(
+                    "The sandbox's private /tmp and network isolation need unprivileged user \
+                     namespaces, which this system does not allow ({}). Turn them off in the \
+                     sandbox settings or allow user namespaces (kernel.unprivileged_userns_clone, \
+                     AppArmor's userns restriction).",
+                    io::Error::from_raw_os_error(errno)
+                )),
+                _ => Err("Checking for user namespace support crashed.".to_string()),
+            }
+        })
+    }
+
+    fn landlock_abi() -> io::Result<libc::c_long> {
+        // SAFETY: querying the version takes no ruleset attribute
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(abi)
+        }
+    }
+
+    // Everything the child needs, prepared before fork so entering allocates nothing
+    struct Prepared {
+        namespaces: libc::c_int,
+        private_tmp: bool,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        handled_access: u64,
+        writable_paths: Vec<CString>,
+        filter: Vec<libc::sock_filter>,
+    }
+
+    pub fn apply_to(profile: &SandboxProfile, cmd: &mut tokio::process::Command) -> io::Result<()> {
+        let abi = landlock_abi()?;
+        let mut handled_access = ACCESS_FS_WRITE_FILE
+            | ACCESS_FS_REMOVE_DIR
+            | ACCESS_FS_REMOVE_FILE
+            | ACCESS_FS_MAKE_CHAR
+            | ACCESS_FS_MAKE_DIR
+            | ACCESS_FS_MAKE_REG
+            | ACCESS_FS_MAKE_SOCK
+            | ACCESS_FS_MAKE_FIFO
+            | ACCESS_FS_MAKE_BLOCK
+            | ACCESS_FS_MAKE_SYM;
+        if abi >= 2 {
+            handled_access |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            handled_access |= ACCESS_FS_TRUNCATE;
+        }
+
+        let mut namespaces = 0;
+        if profile.private_tmp {
+            namespaces |= libc::CLONE_NEWNS;
+        }
+        if profile.deny_network {
+            namespaces |= libc::CLONE_NEWNET;
+        }
+
+        let mut writable: Vec<&str> = ALWAYS_WRITABLE.to_vec();
+        if profile.private_tmp {
+            writable.push("/tmp");
+        }
+        writable.extend(profile.writable_paths.iter().map(String::as_str));
+        let writable_paths = writable
+            .into_iter()
+            .map(|path| CString::new(path).map_err(io::Error::other))
+            .collect::<io::Result<_>>()?;
+
+        let prepared = Prepared {
+            handled_access,
+            writable_paths,
+            filter: seccomp_filter(),
+            ..Prepared::for_namespaces(namespaces, profile.private_tmp)
+        };
+        // SAFETY: entering only makes async-signal-safe system calls on data
+        // prepared above, and does not allocate
+        unsafe {
+            cmd.pre_exec(move || prepared.enter());
+        }
+        Ok(())
+    }
+
+    // SAFETY (for the unsafe blocks below): the calls only take pointers to live
+    // locals and fields, and are all async-signal-safe
+    impl Prepared {
+        // Only the namespace part, with an empty filesystem ruleset and filter
+        fn for_namespaces(namespaces: libc::c_int, private_tmp: bool) -> Self {
+            // SAFETY: getuid/getgid cannot fail
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Self {
+                namespaces,
+                private_tmp,
+                uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+                gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+                handled_access: 0,
+                writable_paths: Vec::new(),
+                filter: Vec::new(),
+            }
+        }
+
+        fn enter(&self) -> io::Result<()> {
+            self.enter_namespaces()?;
+            // Also sets no_new_privs, which the seccomp filter needs as well
+            self.restrict_filesystem()?;
+            let program = libc::sock_fprog {
+                len: self.filter.len() as libc::c_ushort,
+                filter: self.filter.as_ptr() as *mut libc::sock_filter,
+            };
+            check(unsafe {
+                libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                    &program as *const libc::sock_fprog,
+                )
+            })
+        }
+
+        fn enter_namespaces(&self) -> io::Result<()> {
+            if self.namespaces != 0 {
+                // The user namespace is what lets an unprivileged process create the others
+                check(unsafe { libc::unshare(libc::CLONE_NEWUSER | self.namespaces) })?;
+                write_file(c"/proc/self/setgroups", b"deny")?;
+                write_file(c"/proc/self/uid_map", &self.uid_map)?;
+                write_file(c"/proc/self/gid_map", &self.gid_map)?;
+            }
+            if self.private_tmp {
+                // Keep the tmpfs from propagating back to the parent's mount namespace
+                check(unsafe {
+                    libc::mount(
+                        std::ptr::null(),
+                        c"/".as_ptr(),
+                        std::ptr::null(),
+                        libc::MS_REC | libc::MS_PRIVATE,
+                        std::ptr::null(),
+                    )
+                })?;
+                check(unsafe {
+                    libc::mount(
+                        c"tmpfs".as_ptr(),
+                        c"/tmp".as_ptr(),
+                        c"tmpfs".as_ptr(),
+                        libc::MS_NOSUID | libc::MS_NODEV,
+                        c"mode=1777".as_ptr().cast(),
+                    )
+                })?;
+            }
+            Ok(())
+        }
+
+        fn restrict_filesystem(&self) -> io::Result<()> {
+            let attr = RulesetAttr {
+                handled_access_fs: self.handled_access,
+            };
+            let ruleset = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    &attr as *const RulesetAttr,
+                    std::mem::size_of::<RulesetAttr>(),
+                    0,
+                )
+            };
+            if ruleset < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let ruleset = ruleset as libc::c_int;
+            let result = self.add_rules(ruleset).and_then(|()| {
+                // Landlock requires no_new_privs, or CAP_SYS_ADMIN in the namespace
+                check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) })?;
+                check(unsafe {
+                    libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0) as libc::c_int
+                })
+            });
+            unsafe { libc::close(ruleset) };
+            result
+        }
+
+        fn add_rules(&self, ruleset: libc::c_int) -> io::Result<()> {
+            for path in &self.writable_paths {
+                let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+                let mut result = check(unsafe { libc::fstat(fd, &mut stat) });
+                if result.is_ok() {
+                    let is_dir = stat.st_mode & libc::S_IFMT == libc::S_IFDIR;
+                    let rule = PathBeneathAttr {
+                        allowed_access: if is_dir {
+                            self.handled_access
+                        } else {
+                            self.handled_access & ACCESS_FS_FILE
+                        },
+                        parent_fd: fd,
+                    };
+                    result = check(unsafe {
+                        libc::syscall(
+                            libc::SYS_landlock_add_rule,
+                            ruleset,
+                            LANDLOCK_RULE_PATH_BENEATH,
+                            &rule as *const PathBeneathAttr,
+                            0,
+                        ) as libc::c_int
+                    });
+                }
+                unsafe { libc::close(fd) };
+                result?;
+            }
+            Ok(())
+        }
+    }
+
+    // Kills processes of a foreign architecture and fails the denied calls with EPERM,
+    // as well as clone with namespace flags
+    fn seccomp_filter() -> Vec<libc::sock_filter> {
+        let load = |offset| bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset);
+        let deny = bpf_stmt(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_ERRNO | libc::EPERM as u32,
+        );
+        let mut filter = vec![
+            load(SECCOMP_DATA_ARCH),
+            bpf_jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                AUDIT_ARCH,
+                1,
+                0,
+            ),
+            bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            load(SECCOMP_DATA_NR),
+        ];
+        // x32 system calls share the x86_64 architecture but have their own numbers
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([
+            bpf_jump(
+                libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+                0x4000_0000,
+                0,
+                1,
+            ),
+            deny,
+        ]);
+        for &syscall in DENIED_SYSCALLS {
+            filter.push(bpf_jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                syscall as u32,
+                0,
+                1,
+            ));
+            filter.push(deny);
+        }
+        // clone3 passes its flags in memory, out of the filter's reach. ENOSYS makes
+        // libc fall back to clone, whose flags are checked below
+        filter.extend([
+            bpf_jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                libc::SYS_clone3 as u32,
+                0,
+                1,
+            ),
+            bpf_stmt(
+                libc::BPF_RET | libc::BPF_K,
+                libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32,
+            ),
+            bpf_jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                libc::SYS_clone as u32,
+                0,
+                3,
+            ),
+            load(SECCOMP_DATA_ARG0),
+            bpf_jump(
+                libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K,
+                NAMESPACE_FLAGS as u32,
+                0,
+                1,
+            ),
+            deny,
+        ]);
+        filter.push(bpf_stmt(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_ALLOW,
+        ));
+        filter
+    }
+
+    fn bpf_stmt(code: u32, k: u32) -> libc::sock_filter {
+        bpf_jump(code, k, 0, 0)
+    }
+
+    fn bpf_jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    fn write_file(path: &std::ffi::CStr, contents: &[u8]) -> io::Result<()> {
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = unsafe { libc::write(fd, contents.as_ptr().cast(), contents.len()) };
+        let result = if written < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        unsafe { libc::close(fd) };
+        result
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Runs the classic BPF subset the filter uses against one system call
+        fn run_filter(filter: &[libc::sock_filter], arch: u32, nr: i64, arg0: u64) -> u32 {
+            let mut data = [0u8; 64];
+            data[0..4].copy_from_slice(&(nr as u32).to_ne_bytes());
+            data[4..8].copy_from_slice(&arch.to_ne_bytes());
+            data[16..24].copy_from_slice(&arg0.to_ne_bytes());
+            let (mut pc, mut acc) = (0, 0u32);
+            loop {
+                let insn = &filter[pc];
+                let k = insn.k;
+                pc += 1;
+                let code = insn.code as u32;
+                if code == libc::BPF_RET | libc::BPF_K {
+                    return k;
+                }
+                if code == libc::BPF_LD | libc::BPF_W | libc::BPF_ABS {
+                    let offset = k as usize;
+                    acc = u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+                    continue;
+                }
+                let taken = match code & !(libc::BPF_JMP | libc::BPF_K) {
+                    op if op == libc::BPF_JEQ => acc == k,
+                    op if op == libc::BPF_JGE => acc >= k,
+                    op if op == libc::BPF_JSET => acc & k != 0,
+                    _ => panic!("unexpected instruction {:#x}", code),
+                };
+                pc += if taken { insn.jt } else { insn.jf } as usize;
+            }
+        }
+
+        fn verdict(nr: libc::c_long, arg0: u64) -> u32 {
+            run_filter(&seccomp_filter(), AUDIT_ARCH, nr, arg0)
+        }
+
+        const EPERM: u32 = libc::SECCOMP_RET_ERRNO | libc::EPERM as u32;
+
+        #[test]
+        fn filter_denies_listed_calls_and_allows_the_rest() {
+            assert_eq!(verdict(libc::SYS_read, 0), libc::SECCOMP_RET_ALLOW);
+            assert_eq!(verdict(libc::SYS_ptrace, 0), EPERM);
+            assert_eq!(verdict(libc::SYS_unshare, 0), EPERM);
+            assert_eq!(
+                run_filter(&seccomp_filter(), 0x4000_0003, libc::SYS_read, 0),
+                libc::SECCOMP_RET_KILL_PROCESS
+            );
+            #[cfg(target_arch = "x86_64")]
+            assert_eq!(verdict(0x4000_0000 | libc::SYS_read, 0), EPERM);
+        }
+
+        #[test]
+        fn filter_checks_clone_flags_and_refuses_clone3() {
+            let fork = libc::SIGCHLD as u64;
+            let thread = (libc::CLONE_VM | libc::CLONE_THREAD | libc::CLONE_SIGHAND) as u64;
+            assert_eq!(verdict(libc::SYS_clone, fork), libc::SECCOMP_RET_ALLOW);
+            assert_eq!(verdict(libc::SYS_clone, thread), libc::SECCOMP_RET_ALLOW);
+            for flag in [libc::CLONE_NEWUSER, libc::CLONE_NEWNS, libc::CLONE_NEWNET] {
+                assert_eq!(verdict(libc::SYS_clone, fork | flag as u64), EPERM);
+            }
+            assert_eq!(
+                verdict(libc::SYS_clone3, 0),
+                libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32
+            );
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+mod platform {
+    use super::SandboxProfile;
+
+    pub fn check_support(_profile: &SandboxProfile) -> Result<(), String> {
+        Err("The sandbox is only available on Linux (x86_64 and aarch64).".to_string())
+    }
+
+    pub fn apply_to(
+        _profile: &SandboxProfile,
+        _cmd: &mut tokio::process::Command,
+    ) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "The sandbox is not available on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_writable_paths_before_checking_the_system() {
+        let with_paths = |paths: &[&str], private_tmp| SandboxProfile {
+            writable_paths: paths.iter().map(|path| path.to_string()).collect(),
+            private_tmp,
+            ..SandboxProfile::default()
+        };
+        let error = with_paths(&["relative/dir"], false).validate().unwrap_err();
+        assert!(error.contains("must be absolute"), "{}", error);
+        let error = with_paths(&["/daan/no/such/dir"], false)
+            .validate()
+            .unwrap_err();
+        assert!(error.contains("does not exist"), "{}", error);
+        let error = with_paths(&["/tmp"], true).validate().unwrap_err();
+        assert!(error.contains("hidden by the private /tmp"), "{}", error);
+    }
+
+    #[test]
+    fn reports_why_the_system_cannot_sandbox() {
+        // Whatever this machine supports, a refusal must say what is missing
+        if let Err(error) = SandboxProfile::default().validate() {
+            assert!(
+                ["Landlock", "user namespaces", "only available on"]
+                    .iter()
+                    .any(|cause| error.contains(cause)),
+                "{}",
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use crate::mcp::http::{http_client, is_event_stream, HttpEndpoint};
+use crate::mcp::remote::{OutgoingMessage, TransportChannels, TransportEvent};
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, ACCEPT, CONTENT_TYPE};
+use reqwest::Url;
+use tokio::sync::oneshot;
+use tokio::task::JoinSet;
+
+/// One dispatched Server-Sent Event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Value of the `event:` field; `message` when the server did not set one.
+    pub event: String,
+    pub data: String,
+    /// Value of the `id:` field, if this event set one.
+    pub id: Option<String>,
+    /// Reconnection delay requested by the server, in milliseconds.
+    pub retry: Option<u64>,
+}
+
+/// Incremental parser for a `text/event-stream` body.
+///
+/// Bytes can be fed in arbitrary chunks; events are returned once their
+/// terminating blank line has arrived.
+#[derive(Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    // A chunk ended in '\r', so a '\n' at the start of the next one belongs to it
+    skip_newline: bool,
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseParser {
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if self.skip_newline {
+                self.skip_newline = false;
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+            match byte {
+                b'\r' | b'\n' => {
+                    self.skip_newline = byte == b'\r';
+                    let line = std::mem::take(&mut self.buffer);
+                    if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                        events.push(event);
+                    }
+                }
+                _ => self.buffer.push(byte),
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // Comment, commonly used as a keepalive
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match self.data.as_mut() {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" => self.retry = value.parse().ok(),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let id = self.id.take();
+        let retry = self.retry.take();
+        match self.data.take() {
+            Some(data) => Some(SseEvent {
+                event: event
+                    .filter(|event| !event.is_empty())
+                    .unwrap_or_else(|| "message".to_string()),
+                data,
+                id,
+                retry,
+            }),
+            // No data: nothing to dispatch, but id/retry still reach the caller
+            None if id.is_some() || retry.is_some() => Some(SseEvent {
+                event: String::new(),
+                data: String::new(),
+                id,
+                retry,
+            }),
+            None => None,
+        }
+    }
+}
+
+// The `endpoint` event names the URL for POSTs, usually relative to the stream.
+// Headers such as `Authorization` go along with every POST, so only an endpoint
+// on the same origin is accepted.
+fn resolve_endpoint(stream_url: &Url, endpoint: &str) -> Result<Url, String> {
+    let url = stream_url
+        .join(endpoint.trim())
+        .map_err(|e| format!("Invalid endpoint '{}': {}", endpoint, e))?;
+    if url.origin() != stream_url.origin() {
+        return Err(format!(
+            "Endpoint '{}' is not on the same origin as the event stream",
+            url
+        ));
+    }
+    Ok(url)
+}
+
+async fn post_message(
+    client: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+    message: String,
+    reply: oneshot::Sender<Result<(), String>>,
+) {
+    let result = match client
+        .post(url)
+        .headers(headers)
+        .header(CONTENT_TYPE, "application/json")
+        .body(message)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            Err(format!("Server responded with {}: {}", status, body.trim()))
+        }
+        Err(e) => Err(format!("Request failed: {}", e)),
+    };
+    let _ = reply.send(result);
+}
+
+/// Transport task for servers using the HTTP+SSE transport of protocol revision
+/// 2024-11-05: holds the event stream open, POSTs outgoing messages to the URL
+/// from its `endpoint` event, and relays its `message` events.
+///
+/// Messages are queued until the endpoint is known. The session lives as long as
+/// the stream, so the connection closes once the stream ends.
+pub async fn run_legacy_sse(endpoint: HttpEndpoint, channels: TransportChannels) {
+    let TransportChannels {
+        mut outgoing,
+        mut stop,
+        events,
+    } = channels;
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            let _ = events.send(TransportEvent::Closed(e)).await;
+            return;
+        }
+    };
+
+    let response = match client
+        .get(endpoint.url.clone())
+        .headers(endpoint.headers.clone())
+        .header(ACCEPT, "text/event-stream")
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() && is_event_stream(&response) => response,
+        Ok(response) => {
+            let reason = format!(
+                "Server responded with {} instead of an event stream",
+                response.status()
+            );
+            let _ = events.send(TransportEvent::Closed(reason)).await;
+            return;
+        }
+        Err(e) => {
+            let reason = format!("Failed to open event stream: {}", e);
+            let _ = events.send(TransportEvent::Closed(reason)).await;
+            return;
+        }
+    };
+
+    let mut body = response.bytes_stream();
+    let mut parser = SseParser::default();
+    let mut post_url: Option<Url> = None;
+    // Dropping the set aborts POSTs still in flight
+    let mut posts = JoinSet::new();
+    let reason = 'stream: loop {
+        tokio::select! {
+            chunk = body.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    for event in parser.feed(&chunk) {
+                        match event.event.as_str() {
+                            "endpoint" => match resolve_endpoint(&endpoint.url, &event.data) {
+                                Ok(url) => {
+                                    println!("Event stream {} posts to {}.", endpoint.url, url);
+                                    post_url = Some(url);
+                                }
+                                Err(e) => break 'stream e,
+                            },
+                            "message" if !event.data.trim().is_empty() => {
+                                let _ = events.send(TransportEvent::Message(event.data)).await;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Some(Err(e)) => break format!("Event stream broke: {}", e),
+                None => break "Event stream ended".to_string(),
+            },
+            message = outgoing.recv(), if post_url.is_some() => match (message, &post_url) {
+                (Some(OutgoingMessage { message, reply }), Some(url)) => {
+                    posts.spawn(post_message(
+                        client.clone(),
+                        url.clone(),
+                        endpoint.headers.clone(),
+                        message,
+                        reply,
+                    ));
+                }
+                _ => break "Connection closed".to_string(),
+            },
+            Some(_) = posts.join_next() => {}
+            _ = &mut stop => break "Connection closed".to_string(),
+        }
+    };
+
+    posts.shutdown().await;
+    let _ = events.send(TransportEvent::Closed(reason)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    fn feed_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut parser = SseParser::default();
+        chunks.iter().flat_map(|chunk| parser.feed(chunk)).collect()
+    }
+
+    fn message(data: &str) -> SseEvent {
+        SseEvent {
+            event: "message".to_string(),
+            data: data.to_string(),
+            ..SseEvent::default()
+        }
+    }
+
+    #[test]
+    fn parses_events_split_across_chunks() {
+        let events = feed_all(&[b"event: endp", b"oint\nda", b"ta: /messages?s=1\n", b"\n"]);
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: "endpoint".to_string(),
+                data: "/messages?s=1".to_string(),
+                ..SseEvent::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn treats_crlf_as_one_line_break_across_chunks() {
+        // A '\r' at the end of a chunk and the '\n' starting the next must not
+        // count as a blank line, which would dispatch "a" on its own
+        let events = feed_all(&[b"data: a\r", b"\ndata: b\r\n", b"\r", b"\n"]);
+        assert_eq!(events, vec![message("a\nb")]);
+        assert_eq!(
+            feed_all(&[b"data: x\r\rdata: y\r\r"]),
+            vec![message("x"), message("y")]
+        );
+    }
+
+    #[test]
+    fn joins_multi_line_data_and_skips_comments() {
+        let events = feed_all(&[b": keepalive\ndata: first\ndata:second\ndata\n\n"]);
+        assert_eq!(events, vec![message("first\nsecond\n")]);
+        // Without a data field there is nothing to dispatch
+        assert!(feed_all(&[b"event: ping\n\n: keepalive\n\n"]).is_empty());
+    }
+
+    #[test]
+    fn reports_retry_and_id() {
+        let events = feed_all(&[b"retry: 3000\n\nid: 7\nretry: soon\ndata: x\n\n"]);
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    retry: Some(3000),
+                    ..SseEvent::default()
+                },
+                SseEvent {
+                    id: Some("7".to_string()),
+                    ..message("x")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_relative_endpoints_on_the_same_origin() {
+        let stream = Url::parse("http://localhost:8080/mcp/sse").unwrap();
+        assert_eq!(
+            resolve_endpoint(&stream, "/messages?sessionId=1")
+                .unwrap()
+                .as_str(),
+            "http://localhost:8080/messages?sessionId=1"
+        );
+        assert_eq!(
+            resolve_endpoint(&stream, " messages?sessionId=1\n")
+                .unwrap()
+                .as_str(),
+            "http://localhost:8080/mcp/messages?sessionId=1"
+        );
+        assert!(resolve_endpoint(&stream, "https://evil.example/messages").is_err());
+        assert!(resolve_endpoint(&stream, "http://localhost:9090/messages").is_err());
+        assert!(resolve_endpoint(&stream, "//evil.example/messages").is_err());
+    }
+
+    // Reads one request and returns its request line and body.
+    async fn read_request(socket: &mut TcpStream) -> (String, String) {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    let line = head.lines().next().unwrap();
+                    let line = line.trim_end_matches(" HTTP/1.1").to_string();
+                    return (line, body.to_string());
+                }
+            }
+            assert!(n > 0, "connection closed mid-request");
+        }
+    }
+
+    // Minimal stand-in for a legacy SSE server: the first connection is the event
+    // stream, which writes whatever `stream` is sent; later ones are POSTs.
+    async fn serve(
+        listener: TcpListener,
+        mut stream: mpsc::Receiver<String>,
+        seen: mpsc::Sender<String>,
+    ) {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let (line, _) = read_request(&mut socket).await;
+        seen.send(line).await.unwrap();
+        socket
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            while let Some(chunk) = stream.recv().await {
+                socket.write_all(chunk.as_bytes()).await.unwrap();
+            }
+        });
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let (line, body) = read_request(&mut socket).await;
+            seen.send(format!("{} {}", line, body)).await.unwrap();
+            socket
+                .write_all(
+                    b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn posts_only_once_the_endpoint_is_known() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        let (stream, stream_receiver) = mpsc::channel(8);
+        let (seen_sender, mut seen) = mpsc::channel(8);
+        tokio::spawn(serve(listener, stream_receiver, seen_sender));
+
+        let (outgoing, outgoing_receiver) = mpsc::channel(8);
+        let (stop_signal, stop) = oneshot::channel();
+        let (events_sender, mut events) = mpsc::channel(8);
+        let endpoint = HttpEndpoint::new(&url, &HashMap::new()).unwrap();
+        tokio::spawn(run_legacy_sse(
+            endpoint,
+            TransportChannels {
+                outgoing: outgoing_receiver,
+                stop,
+                events: events_sender,
+            },
+        ));
+        assert_eq!(seen.recv().await.unwrap(), "GET /sse");
+
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#;
+        let (reply, result) = oneshot::channel();
+        outgoing
+            .send(OutgoingMessage {
+                message: request.to_string(),
+                reply,
+            })
+            .await
+            .unwrap();
+        // Queued until the server names the endpoint
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), seen.recv())
+                .await
+                .is_err()
+        );
+
+        stream
+            .send(": connected\n\nevent: endpoint\ndata: /messages?sessionId=1\n\n".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            seen.recv().await.unwrap(),
+            format!("POST /messages?sessionId=1 {}", request)
+        );
+        assert_eq!(result.await.unwrap(), Ok(()));
+
+        let response = r#"{"jsonrpc":"2.0","id":1,"result":{}}"#;
+        stream
+            .send(format!("event: message\r\ndata: {}\r\n\r\n", response))
+            .await
+            .unwrap();
+        match events.recv().await {
+            Some(TransportEvent::Message(message)) => assert_eq!(message, response),
+            other => panic!("expected a message, got {:?}", other),
+        }
+
+        stop_signal.send(()).unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(TransportEvent::Closed(_))
+        ));
+    }
+}
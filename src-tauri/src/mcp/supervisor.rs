@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// A run that lasted at least this long resets the consecutive failure counter,
+/// so a server that crashes once a day is not eventually given up on.
+pub const RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// When a process that exited on its own should be started again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RestartMode {
+    #[default]
+    Never,
+    /// Restart after a non-zero exit, a signal, or a failed wait.
+    OnFailure,
+    /// Restart after any exit that was not requested through `stop_external_process`.
+    Always,
+}
+
+/// Per-process restart settings, part of `ProcessOptions`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RestartPolicy {
+    pub mode: RestartMode,
+    /// Consecutive restarts allowed before giving up.
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            mode: RestartMode::Never,
+            max_retries: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn should_restart(&self, wait_result: &Result<ExitStatus, std::io::Error>) -> bool {
+        match self.mode {
+            RestartMode::Never => false,
+            RestartMode::OnFailure => !matches!(wait_result, Ok(status) if status.success()),
+            RestartMode::Always => true,
+        }
+    }
+
+    /// Delay before the given restart attempt (1-based): the initial backoff,
+    /// doubled on every attempt and capped at `max_backoff_ms`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms.max(self.initial_backoff_ms)),
+        )
+    }
+}
+
+/// Payload of the `process_restarting_<id>` event, sent before the backoff delay.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartingEvent {
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
@@ -0,0 +1,382 @@
+use crate::mcp::http::header_map;
+use crate::mcp::remote::{OutgoingMessage, RemoteOptions, TransportChannels, TransportEvent};
+use crate::mcp::supervisor::{RestartMode, RestartPolicy, RestartingEvent, RESTART_RESET_AFTER};
+use futures::{SinkExt, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use reqwest::Url;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Default interval between keepalive pings. A connection that has not answered
+/// the previous ping by the next one is considered dead.
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Limit for the TCP connect and WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Subprotocol MCP servers expect in the handshake.
+const MCP_SUBPROTOCOL: &str = "mcp";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Endpoint and keepalive/reconnect settings of a WebSocket server.
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub ping_interval: Duration,
+    pub reconnect: RestartPolicy,
+}
+
+impl WebSocketConfig {
+    pub fn new(url: &str, options: &RemoteOptions) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+        if !matches!(url.scheme(), "ws" | "wss") {
+            return Err(format!("Unsupported URL scheme '{}'.", url.scheme()));
+        }
+        Ok(Self {
+            url,
+            headers: header_map(&options.headers)?,
+            ping_interval: options
+                .ping_interval_ms
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_PING_INTERVAL),
+            // Unlike processes, connections are reconnected unless configured otherwise
+            reconnect: options.reconnect.clone().unwrap_or(RestartPolicy {
+                mode: RestartMode::Always,
+                ..RestartPolicy::default()
+            }),
+        })
+    }
+}
+
+// How one connected session ended
+enum SessionEnd {
+    Stopped,
+    // The server sent a close frame; `clean` if it was a normal closure
+    Closed { reason: String, clean: bool },
+    Failed(String),
+}
+
+async fn connect(config: &WebSocketConfig) -> Result<Socket, String> {
+    let mut request = config
+        .url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| format!("Invalid WebSocket request: {}", e))?;
+    request.headers_mut().extend(config.headers.clone());
+    request.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(MCP_SUBPROTOCOL),
+    );
+
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, tokio_tungstenite::connect_async(request)).await {
+        Ok(Ok((socket, _))) => Ok(socket),
+        Ok(Err(e)) => Err(format!("Failed to connect: {}", e)),
+        Err(_) => Err(format!(
+            "Timed out after {:?} connecting to {}",
+            HANDSHAKE_TIMEOUT, config.url
+        )),
+    }
+}
+
+// Relays frames both ways and keeps the connection alive until it ends.
+async fn run_session(
+    mut socket: Socket,
+    ping_interval: Duration,
+    outgoing: &mut mpsc::Receiver<OutgoingMessage>,
+    stop: &mut oneshot::Receiver<()>,
+    events: &mpsc::Sender<TransportEvent>,
+) -> SessionEnd {
+    let mut ping = tokio::time::interval(ping_interval);
+    // The first tick fires immediately
+    ping.tick().await;
+    let mut awaiting_pong = false;
+
+    loop {
+        tokio::select! {
+            frame = socket.next() => match frame {
+                Some(Ok(Message::Text(text))) => {
+                    let _ = events.send(TransportEvent::Message(text.to_string())).await;
+                }
+                Some(Ok(Message::Binary(bytes))) => match String::from_utf8(bytes.to_vec()) {
+                    Ok(text) => {
+                        let _ = events.send(TransportEvent::Message(text)).await;
+                    }
+                    Err(_) => {
+                        let error = "Ignored a binary frame that is not UTF-8".to_string();
+                        let _ = events.send(TransportEvent::Error(error)).await;
+                    }
+                },
+                Some(Ok(Message::Pong(_))) => awaiting_pong = false,
+                Some(Ok(Message::Close(frame))) => {
+                    return match frame {
+                        Some(frame) => SessionEnd::Closed {
+                            reason: format!(
+                                "Server closed the connection ({}): {}",
+                                frame.code, frame.reason
+                            ),
+                            clean: frame.code == CloseCode::Normal,
+                        },
+                        None => SessionEnd::Closed {
+                            reason: "Server closed the connection".to_string(),
+                            clean: true,
+                        },
+                    };
+                }
+                // Pings are answered by tungstenite itself
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return SessionEnd::Failed(format!("Connection lost: {}", e)),
+                None => return SessionEnd::Failed("Connection lost".to_string()),
+            },
+            message = outgoing.recv() => match message {
+                Some(OutgoingMessage { message, reply }) => {
+                    if let Err(e) = socket.send(Message::text(message)).await {
+                        let error = format!("Failed to send message: {}", e);
+                        let _ = reply.send(Err(error.clone()));
+                        return SessionEnd::Failed(error);
+                    }
+                    let _ = reply.send(Ok(()));
+                }
+                None => {
+                    let _ = socket.close(None).await;
+                    return SessionEnd::Stopped;
+                }
+            },
+            _ = ping.tick() => {
+                if awaiting_pong {
+                    return SessionEnd::Failed(format!(
+                        "No pong received within {:?}",
+                        ping_interval
+                    ));
+                }
+                awaiting_pong = true;
+                if let Err(e) = socket.send(Message::Ping(Default::default())).await {
+                    return SessionEnd::Failed(format!("Failed to send ping: {}", e));
+                }
+            }
+            _ = &mut *stop => {
+                let _ = socket.close(None).await;
+                return SessionEnd::Stopped;
+            }
+        }
+    }
+}
+
+// Waits out the reconnect backoff, failing messages sent in the meantime.
+// Returns false if the connection was stopped.
+async fn wait_before_reconnect(
+    delay: Duration,
+    outgoing: &mut mpsc::Receiver<OutgoingMessage>,
+    stop: &mut oneshot::Receiver<()>,
+) -> bool {
+    let sleep = tokio::time::sleep(delay);
+    tokio::pin!(sleep);
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return true,
+            message = outgoing.recv() => match message {
+                Some(OutgoingMessage { reply, .. }) => {
+                    let _ = reply.send(Err("Not connected, waiting to reconnect".to_string()));
+                }
+                None => return false,
+            },
+            _ = &mut *stop => return false,
+        }
+    }
+}
+
+/// Transport task of a WebSocket connection: one JSON-RPC message per text frame,
+/// pings every `ping_interval`, and reconnects with backoff per `reconnect`.
+///
+/// A reconnected socket is a new session on the server, so the frontend is told
+/// through the same restarting/restarted events as a restarted process.
+pub async fn run_websocket(config: WebSocketConfig, channels: TransportChannels) {
+    let TransportChannels {
+        mut outgoing,
+        mut stop,
+        events,
+    } = channels;
+    let policy = &config.reconnect;
+    let mut failures: u32 = 0;
+    let mut connected_before = false;
+    let mut reconnects: u32 = 0;
+
+    let close_reason = loop {
+        let attempt_started = tokio::time::Instant::now();
+        let end = match connect(&config).await {
+            Ok(socket) => {
+                println!("WebSocket connected to {}.", config.url);
+                if connected_before {
+                    reconnects += 1;
+                    let _ = events.send(TransportEvent::Reconnected(reconnects)).await;
+                }
+                connected_before = true;
+                run_session(
+                    socket,
+                    config.ping_interval,
+                    &mut outgoing,
+                    &mut stop,
+                    &events,
+                )
+                .await
+            }
+            Err(e) => SessionEnd::Failed(e),
+        };
+
+        let reason = match end {
+            SessionEnd::Stopped => break "Connection closed".to_string(),
+            SessionEnd::Closed { reason, clean } => {
+                if clean && policy.mode != RestartMode::Always {
+                    break reason;
+                }
+                reason
+            }
+            SessionEnd::Failed(reason) => reason,
+        };
+        eprintln!("WebSocket connection to {} ended: {}", config.url, reason);
+        if policy.mode == RestartMode::Never {
+            break reason;
+        }
+        if attempt_started.elapsed() >= RESTART_RESET_AFTER {
+            failures = 0;
+        }
+        if failures >= policy.max_retries {
+            break format!("{}; gave up after {} reconnect attempts", reason, failures);
+        }
+
+        failures += 1;
+        let delay = policy.backoff(failures);
+        let _ = events
+            .send(TransportEvent::Reconnecting(RestartingEvent {
+                attempt: failures,
+                max_retries: policy.max_retries,
+                delay_ms: delay.as_millis() as u64,
+                reason,
+            }))
+            .await;
+        if !wait_before_reconnect(delay, &mut outgoing, &mut stop).await {
+            break "Connection closed".to_string();
+        }
+    };
+
+    let _ = events.send(TransportEvent::Closed(close_reason)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    // The client fails the handshake unless the server agrees on the subprotocol.
+    // The signature is tungstenite's, error type included.
+    #[allow(clippy::result_large_err)]
+    fn accept_mcp(_: &Request, mut response: Response) -> Result<Response, ErrorResponse> {
+        response.headers_mut().insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(MCP_SUBPROTOCOL),
+        );
+        Ok(response)
+    }
+
+    // Echoes text frames. The first connection is dropped, without a close frame,
+    // right after its first echo.
+    async fn serve(listener: TcpListener) {
+        for connection in 1.. {
+            let Ok((stream, _)) = listener.accept().await else {
+                return;
+            };
+            let mut socket = tokio_tungstenite::accept_hdr_async(stream, accept_mcp)
+                .await
+                .unwrap();
+            tokio::spawn(async move {
+                while let Some(Ok(frame)) = socket.next().await {
+                    if frame.is_text() {
+                        socket.send(frame).await.unwrap();
+                        if connection == 1 {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    async fn send(outgoing: &mpsc::Sender<OutgoingMessage>, message: &str) -> Result<(), String> {
+        let (reply, result) = oneshot::channel();
+        outgoing
+            .send(OutgoingMessage {
+                message: message.to_string(),
+                reply,
+            })
+            .await
+            .unwrap();
+        result.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_server_drops_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener));
+
+        let options = RemoteOptions {
+            reconnect: Some(RestartPolicy {
+                mode: RestartMode::Always,
+                initial_backoff_ms: 10,
+                ..RestartPolicy::default()
+            }),
+            ..RemoteOptions::default()
+        };
+        let (outgoing, outgoing_receiver) = mpsc::channel(8);
+        let (stop_signal, stop) = oneshot::channel();
+        let (events_sender, mut events) = mpsc::channel(8);
+        tokio::spawn(run_websocket(
+            WebSocketConfig::new(&url, &options).unwrap(),
+            TransportChannels {
+                outgoing: outgoing_receiver,
+                stop,
+                events: events_sender,
+            },
+        ));
+
+        let first = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        send(&outgoing, first).await.unwrap();
+        match events.recv().await {
+            Some(TransportEvent::Message(message)) => assert_eq!(message, first),
+            other => panic!("expected the echo, got {:?}", other),
+        }
+
+        match events.recv().await {
+            Some(TransportEvent::Reconnecting(event)) => {
+                assert_eq!(event.attempt, 1);
+                assert!(event.reason.contains("Connection lost"), "{}", event.reason);
+            }
+            other => panic!("expected a reconnect, got {:?}", other),
+        }
+        assert!(matches!(
+            events.recv().await,
+            Some(TransportEvent::Reconnected(1))
+        ));
+
+        let second = r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#;
+        send(&outgoing, second).await.unwrap();
+        match events.recv().await {
+            Some(TransportEvent::Message(message)) => assert_eq!(message, second),
+            other => panic!("expected the echo, got {:?}", other),
+        }
+
+        stop_signal.send(()).unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(TransportEvent::Closed(_))
+        ));
+    }
+}
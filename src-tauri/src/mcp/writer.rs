@@ -0,0 +1,120 @@
+use crate::mcp::framing::Framing;
+use crate::mcp::inspector::TrafficTap;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::{mpsc, oneshot};
+
+/// Messages that may wait for the writer before senders are held back.
+pub const STDIN_QUEUE_CAPACITY: usize = 64;
+
+/// Default limit for queueing and for writing a single message.
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct WriteRequest {
+    line: String,
+    reply: oneshot::Sender<Result<(), String>>,
+}
+
+/// Handle to the task that owns a process's stdin.
+///
+/// All writes go through one bounded queue, so concurrent senders are written in
+/// FIFO order without interleaving, and each sender learns whether its own
+/// message made it. Stdin is closed once every handle has been dropped and the
+/// queue is drained.
+#[derive(Clone)]
+pub struct StdinWriter {
+    sender: mpsc::Sender<WriteRequest>,
+    write_timeout: Duration,
+    framing: Framing,
+    tap: TrafficTap,
+}
+
+impl StdinWriter {
+    pub fn spawn(
+        stdin: ChildStdin,
+        process_id: String,
+        write_timeout: Duration,
+        framing: Framing,
+        tap: TrafficTap,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(STDIN_QUEUE_CAPACITY);
+        tokio::spawn(run_writer(stdin, receiver, process_id, write_timeout));
+        Self {
+            sender,
+            write_timeout,
+            framing,
+            tap,
+        }
+    }
+
+    /// Queues `message`, framed as the process expects, and resolves once it has
+    /// been written. Waits for queue space if the process is not keeping up.
+    pub async fn send(&self, message: String) -> Result<(), String> {
+        let (reply, written) = oneshot::channel();
+        self.tap.outbound(&message);
+        let request = WriteRequest {
+            line: self.framing.encode(message),
+            reply,
+        };
+
+        tokio::time::timeout(self.write_timeout, self.sender.send(request))
+            .await
+            .map_err(|_| {
+                format!(
+                    "Timed out after {:?} waiting for space in the stdin queue",
+                    self.write_timeout
+                )
+            })?
+            .map_err(|_| "Stdin writer has stopped".to_string())?;
+
+        written
+            .await
+            .map_err(|_| "Stdin writer stopped before the message was written".to_string())?
+    }
+}
+
+async fn run_writer(
+    mut stdin: ChildStdin,
+    mut receiver: mpsc::Receiver<WriteRequest>,
+    process_id: String,
+    write_timeout: Duration,
+) {
+    // After a failed or timed-out write the stream may hold half a message,
+    // so everything queued after it is rejected instead of written
+    let mut broken: Option<String> = None;
+
+    while let Some(request) = receiver.recv().await {
+        let result = match &broken {
+            Some(reason) => Err(reason.clone()),
+            None => {
+                let write = async {
+                    stdin.write_all(request.line.as_bytes()).await?;
+                    stdin.flush().await
+                };
+                match tokio::time::timeout(write_timeout, write).await {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(e)) => {
+                        eprintln!("Failed to write to stdin for process {}: {}", process_id, e);
+                        let reason = format!("Failed to write to stdin: {}", e);
+                        broken = Some(reason.clone());
+                        Err(reason)
+                    }
+                    Err(_) => {
+                        eprintln!(
+                            "Write to stdin for process {} timed out after {:?}.",
+                            process_id, write_timeout
+                        );
+                        broken = Some("Stdin is unusable after an earlier write timed out".to_string());
+                        Err(format!("Write to stdin timed out after {:?}", write_timeout))
+                    }
+                }
+            }
+        };
+        // The sender may have given up waiting; nothing to report to then
+        let _ = request.reply.send(result);
+    }
+
+    println!("Stdin writer task finished for {}.", process_id);
+    // stdin is dropped here, which closes the pipe
+}
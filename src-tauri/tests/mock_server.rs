@@ -0,0 +1,333 @@
+//! Drives the bundled `mock-mcp-server` through the process manager and the
+//! native MCP client, covering the failure paths real servers hit.
+
+use std::time::Duration;
+
+use app_lib::mcp::client::McpClient;
+use app_lib::mcp::cmd::{start_process, stop_process};
+use app_lib::mcp::control::{ProcessOptions, ProcessRegistry};
+use app_lib::mcp::events::{ExitInfo, ProcessEvent, ProcessEvents};
+use app_lib::mcp::framing::FramingMode;
+use app_lib::mcp::inspector::{ExchangeKind, TrafficQuery};
+use app_lib::mcp::logs::PROCESS_LOG_CAPACITY;
+use app_lib::mcp::recorder::{read_recording, Direction};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+const MOCK_SERVER: &str = env!("CARGO_BIN_EXE_mock-mcp-server");
+const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Mock {
+    id: String,
+    registry: ProcessRegistry,
+    events: mpsc::UnboundedReceiver<ProcessEvent>,
+}
+
+impl Mock {
+    async fn start(args: &[&str], options: ProcessOptions) -> Self {
+        let registry = ProcessRegistry::default();
+        let (sender, events) = mpsc::unbounded_channel();
+        let id = start_process(
+            MOCK_SERVER.to_string(),
+            args.iter().map(|arg| arg.to_string()).collect(),
+            options,
+            ProcessEvents::new(sender),
+            &registry,
+        )
+        .await
+        .expect("start mock server");
+        Self {
+            id,
+            registry,
+            events,
+        }
+    }
+
+    fn client(&self) -> McpClient {
+        McpClient::new(self.registry.clone(), self.id.clone())
+    }
+
+    async fn call(&self, name: &str, arguments: Value) -> Result<Value, String> {
+        self.client().call_tool(name, arguments, CALL_TIMEOUT).await
+    }
+
+    async fn closed(&mut self) -> ExitInfo {
+        loop {
+            let event = tokio::time::timeout(EVENT_TIMEOUT, self.events.recv())
+                .await
+                .expect("timed out waiting for the process to close")
+                .expect("event sink closed");
+            if let ProcessEvent::Closed(info) = event {
+                return info;
+            }
+        }
+    }
+
+    async fn stop(mut self) -> ExitInfo {
+        stop_process(&self.registry, &self.id, None).unwrap();
+        self.closed().await
+    }
+}
+
+fn echoed_text(result: &Value) -> &str {
+    result["content"][0]["text"].as_str().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn initializes_and_calls_tools() {
+    let mock = Mock::start(&[], ProcessOptions::default()).await;
+
+    let session = mock.client().initialize().await.unwrap();
+    assert!(session.supports("tools"));
+    let tools = mock.client().list_tools().await.unwrap();
+    let names: Vec<&str> = tools.iter().filter_map(|t| t["name"].as_str()).collect();
+    assert_eq!(names, ["echo", "fault"]);
+
+    let result = mock.call("echo", json!({ "text": "hi" })).await.unwrap();
+    assert_eq!(echoed_text(&result), r#"{"text":"hi"}"#);
+
+    let info = mock.stop().await;
+    assert!(info.stop_requested);
+    assert_eq!(info.code, Some(0));
+}
+
+#[tokio::test]
+async fn serves_scenario_tools_resources_and_prompts() {
+    let scenario = std::env::temp_dir().join(format!(
+        "daan-mock-scenario-{}.json",
+        uuid::Uuid::new_v4()
+    ));
+    let contents = json!({
+        "serverName": "scripted",
+        "banner": "scripted server starting",
+        "tools": [{ "name": "weather", "content": [{ "type": "text", "text": "sunny" }] }],
+        "resources": [{ "uri": "mock://readme", "name": "readme", "text": "hello" }],
+        "prompts": [{ "name": "greet", "text": "Say hello" }],
+    });
+    std::fs::write(&scenario, contents.to_string()).unwrap();
+    let mock = Mock::start(
+        &["--scenario", &scenario.to_string_lossy()],
+        ProcessOptions::default(),
+    )
+    .await;
+
+    let result = mock.call("weather", json!({})).await.unwrap();
+    assert_eq!(echoed_text(&result), "sunny");
+
+    let client = mock.client();
+    let read = client
+        .request(
+            "resources/read",
+            Some(json!({ "uri": "mock://readme" })),
+            CALL_TIMEOUT,
+        )
+        .await
+        .unwrap();
+    assert_eq!(read["contents"][0]["text"], "hello");
+    let prompt = client
+        .request("prompts/get", Some(json!({ "name": "greet" })), CALL_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(prompt["messages"][0]["content"]["text"], "Say hello");
+
+    let info = mock.registry.lock().unwrap()[&mock.id].info(&mock.id);
+    assert_eq!(info.ignored_stdout_lines, 1);
+
+    mock.stop().await;
+    let _ = std::fs::remove_file(scenario);
+}
+
+#[tokio::test]
+async fn reports_injected_errors() {
+    let mock = Mock::start(&[], ProcessOptions::default()).await;
+
+    let error = mock
+        .call(
+            "fault",
+            json!({ "faults": [{ "kind": "error", "code": -32001, "message": "nope" }] }),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error, "MCP error -32001: nope");
+
+    mock.stop().await;
+}
+
+#[tokio::test]
+async fn times_out_hung_requests_and_keeps_serving() {
+    let mock = Mock::start(&[], ProcessOptions::default()).await;
+    mock.client().initialize().await.unwrap();
+
+    let error = mock
+        .client()
+        .call_tool(
+            "fault",
+            json!({ "faults": [{ "kind": "hang" }] }),
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap_err();
+    assert!(error.contains("timed out"), "{}", error);
+
+    let result = mock.call("echo", json!({ "after": "hang" })).await.unwrap();
+    assert_eq!(echoed_text(&result), r#"{"after":"hang"}"#);
+
+    mock.stop().await;
+}
+
+#[tokio::test]
+async fn ignores_malformed_output() {
+    let mock = Mock::start(&[], ProcessOptions::default()).await;
+    mock.client().initialize().await.unwrap();
+
+    let error = mock
+        .client()
+        .call_tool(
+            "fault",
+            json!({ "faults": [{ "kind": "malformed", "output": "{not json" }] }),
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap_err();
+    assert!(error.contains("timed out"), "{}", error);
+    let info = mock.registry.lock().unwrap()[&mock.id].info(&mock.id);
+    assert_eq!(info.ignored_stdout_lines, 1);
+
+    assert!(mock.call("echo", json!({})).await.is_ok());
+    mock.stop().await;
+}
+
+#[tokio::test]
+async fn reports_crash_during_a_call() {
+    let mut mock = Mock::start(&[], ProcessOptions::default()).await;
+
+    let faults = json!({ "faults": [{ "kind": "crash", "exitCode": 7, "stderr": "boom" }] });
+    let error = mock.call("fault", faults).await.unwrap_err();
+    assert!(error.contains("exited before answering"), "{}", error);
+
+    let info = mock.closed().await;
+    assert_eq!(info.code, Some(7));
+    assert!(!info.stop_requested);
+    assert_eq!(info.stderr_tail, vec!["boom".to_string()]);
+}
+
+#[tokio::test]
+async fn crashes_after_the_configured_number_of_requests() {
+    let mut mock = Mock::start(&["--crash-after", "2"], ProcessOptions::default()).await;
+
+    // initialize is the first request, so the server exits right after this answer
+    mock.call("echo", json!({})).await.unwrap();
+    let info = mock.closed().await;
+    assert_eq!(info.code, Some(1));
+    assert!(mock.call("echo", json!({})).await.is_err());
+}
+
+#[tokio::test]
+async fn bounds_the_log_under_stderr_spam() {
+    let spam = (PROCESS_LOG_CAPACITY * 2).to_string();
+    let mock = Mock::start(&["--stderr-spam", &spam], ProcessOptions::default()).await;
+
+    let faults = json!({ "faults": [{ "kind": "stderr", "lines": 50, "text": "noisy" }] });
+    assert!(mock.call("fault", faults).await.is_ok());
+
+    // stderr is read on its own task, so it may still be catching up
+    let log = mock.registry.process_log(&mock.id).unwrap();
+    let deadline = tokio::time::Instant::now() + EVENT_TIMEOUT;
+    while !log.tail(None).iter().any(|line| line.line.starts_with("noisy (50/50)")) {
+        assert!(tokio::time::Instant::now() < deadline, "stderr never arrived");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(log.tail(None).len(), PROCESS_LOG_CAPACITY);
+    mock.stop().await;
+}
+
+#[tokio::test]
+async fn speaks_content_length_framing() {
+    let options = ProcessOptions {
+        framing: FramingMode::ContentLength,
+        ..Default::default()
+    };
+    let mock = Mock::start(&["--framing", "content-length"], options).await;
+
+    let result = mock.call("echo", json!({ "framed": true })).await.unwrap();
+    assert_eq!(echoed_text(&result), r#"{"framed":true}"#);
+
+    mock.stop().await;
+}
+
+#[tokio::test]
+async fn records_traffic_and_replays_it() {
+    let dir = std::env::temp_dir().join(format!("daan-traces-{}", uuid::Uuid::new_v4()));
+    let options = ProcessOptions {
+        record_traffic: true,
+        record_dir: Some(dir.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+    let mock = Mock::start(&[], options).await;
+    let recorded = mock.call("echo", json!({ "n": 1 })).await.unwrap();
+    let info = mock.registry.lock().unwrap()[&mock.id].info(&mock.id);
+    let recording = info.recording_path.expect("recording path");
+    mock.stop().await;
+
+    let messages = read_recording(recording.as_ref()).unwrap();
+    let methods: Vec<(Direction, Option<&str>)> = messages
+        .iter()
+        .map(|m| (m.direction, m.message["method"].as_str()))
+        .collect();
+    assert_eq!(
+        methods,
+        [
+            (Direction::Outbound, Some("initialize")),
+            (Direction::Inbound, None),
+            (Direction::Outbound, Some("notifications/initialized")),
+            (Direction::Outbound, Some("tools/call")),
+            (Direction::Inbound, None),
+        ]
+    );
+
+    // The client's request ids differ this time; the replay maps them
+    let replay = Mock::start(&["--replay", &recording], ProcessOptions::default()).await;
+    let replayed = replay.call("echo", json!({ "n": 1 })).await.unwrap();
+    assert_eq!(replayed, recorded);
+    let error = replay.call("echo", json!({})).await.unwrap_err();
+    assert!(error.contains("No matching request"), "{}", error);
+
+    replay.stop().await;
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn inspects_requests_with_their_responses() {
+    let mock = Mock::start(&[], ProcessOptions::default()).await;
+    mock.call("echo", json!({ "n": 1 })).await.unwrap();
+    mock.call(
+        "fault",
+        json!({ "faults": [{ "kind": "error", "code": -32000, "message": "boom" }] }),
+    )
+    .await
+    .unwrap_err();
+
+    let inspector = mock.registry.inspector();
+    let page = inspector.query(&TrafficQuery {
+        source_id: Some(mock.id.clone()),
+        kind: Some(ExchangeKind::Request),
+        ..Default::default()
+    });
+    let methods: Vec<&str> = page
+        .exchanges
+        .iter()
+        .filter_map(|exchange| exchange.method.as_deref())
+        .collect();
+    assert_eq!(methods, ["tools/call", "tools/call", "initialize"]);
+    assert!(page.exchanges.iter().all(|exchange| exchange.response.is_some()));
+    assert_eq!(page.exchanges[0].error.as_ref().unwrap()["message"], "boom");
+
+    let errors = inspector.query(&TrafficQuery {
+        errors_only: true,
+        ..Default::default()
+    });
+    assert_eq!(errors.exchanges.len(), 1);
+
+    mock.stop().await;
+}
@@ -0,0 +1,488 @@
+//! Runs the process manager against a scripted echo server, without Tauri.
+#![cfg(unix)]
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use app_lib::mcp::cmd::{send_to_process, start_process, stop_process};
+use app_lib::mcp::control::{ProcessOptions, ProcessRegistry, ShutdownStage};
+use app_lib::mcp::events::{ExitInfo, ProcessEvent, ProcessEvents};
+use app_lib::mcp::limits::{ResourceLimit, ResourceLimits};
+use app_lib::mcp::sandbox::SandboxProfile;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Prints a banner, then answers every line with a result echoing it. The
+/// `stderr` method also writes to stderr, `crash` exits with code 3.
+const ECHO_SERVER: &str = r#"
+echo "echo server ready"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"crash"'*) echo "crashing on request" >&2; exit 3 ;;
+    *'"method":"stderr"'*) echo "diagnostic output" >&2 ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":{"echo":%s}}\n' "$id" "$line"
+done
+"#;
+
+struct EchoServer {
+    script: PathBuf,
+    registry: ProcessRegistry,
+    events: mpsc::UnboundedReceiver<ProcessEvent>,
+}
+
+impl EchoServer {
+    fn new() -> Self {
+        let script =
+            std::env::temp_dir().join(format!("daan-echo-server-{}.sh", uuid::Uuid::new_v4()));
+        std::fs::write(&script, ECHO_SERVER).expect("write echo server script");
+        let (_, events) = mpsc::unbounded_channel();
+        Self {
+            script,
+            registry: ProcessRegistry::default(),
+            events,
+        }
+    }
+
+    fn script(&self) -> String {
+        self.script.to_string_lossy().into_owned()
+    }
+
+    async fn start(&mut self, command: &str, args: Vec<String>) -> Result<String, String> {
+        self.start_with(command, args, ProcessOptions::default())
+            .await
+    }
+
+    async fn start_with(
+        &mut self,
+        command: &str,
+        args: Vec<String>,
+        options: ProcessOptions,
+    ) -> Result<String, String> {
+        let (sender, events) = mpsc::unbounded_channel();
+        self.events = events;
+        start_process(
+            command.to_string(),
+            args,
+            options,
+            ProcessEvents::new(sender),
+            &self.registry,
+        )
+        .await
+    }
+
+    async fn request(&self, id: &str, request_id: u32, method: &str) {
+        let message = format!(
+            r#"{{"jsonrpc":"2.0","id":{},"method":"{}"}}"#,
+            request_id, method
+        );
+        send_to_process(&self.registry, id.to_string(), message)
+            .await
+            .expect("send to echo server");
+    }
+
+    async fn next_event(&mut self) -> ProcessEvent {
+        tokio::time::timeout(EVENT_TIMEOUT, self.events.recv())
+            .await
+            .expect("timed out waiting for a process event")
+            .expect("event sink closed")
+    }
+
+    async fn next_message(&mut self) -> Value {
+        loop {
+            if let ProcessEvent::Message(message) = self.next_event().await {
+                return serde_json::from_str(&message).expect("message is JSON");
+            }
+        }
+    }
+
+    async fn closed(&mut self) -> ExitInfo {
+        loop {
+            if let ProcessEvent::Closed(info) = self.next_event().await {
+                return info;
+            }
+        }
+    }
+}
+
+impl Drop for EchoServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.script);
+    }
+}
+
+#[tokio::test]
+async fn spawns_and_echoes_requests() {
+    let mut server = EchoServer::new();
+    let id = server
+        .start("sh", vec![server.script()])
+        .await
+        .expect("start echo server");
+
+    server.request(&id, 7, "ping").await;
+    let response = server.next_message().await;
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["result"]["echo"]["method"], "ping");
+
+    let info = server.registry.lock().unwrap()[&id].info(&id);
+    assert_eq!(info.command, "sh");
+    assert!(PathBuf::from(&info.resolved_path).is_absolute());
+    assert!(info.pid.is_some());
+    // The banner is not JSON-RPC and only went to the log
+    assert_eq!(info.ignored_stdout_lines, 1);
+
+    stop_process(&server.registry, &id, None).unwrap();
+    server.closed().await;
+}
+
+#[tokio::test]
+async fn reports_stderr() {
+    let mut server = EchoServer::new();
+    let id = server.start("sh", vec![server.script()]).await.unwrap();
+
+    server.request(&id, 1, "stderr").await;
+    loop {
+        match server.next_event().await {
+            ProcessEvent::Stderr(line) => {
+                assert_eq!(line, "diagnostic output");
+                break;
+            }
+            ProcessEvent::Closed(info) => panic!("closed before stderr: {:?}", info),
+            _ => {}
+        }
+    }
+
+    stop_process(&server.registry, &id, None).unwrap();
+    server.closed().await;
+}
+
+#[tokio::test]
+async fn reports_crash_with_exit_code_and_stderr() {
+    let mut server = EchoServer::new();
+    let id = server.start("sh", vec![server.script()]).await.unwrap();
+
+    server.request(&id, 1, "crash").await;
+    let info = server.closed().await;
+    assert_eq!(info.code, Some(3));
+    assert_eq!(info.signal, None);
+    assert!(!info.stop_requested);
+    assert_eq!(info.stderr_tail, vec!["crashing on request".to_string()]);
+    assert!(!server.registry.lock().unwrap().contains_key(&id));
+}
+
+#[tokio::test]
+async fn stops_by_closing_stdin() {
+    let mut server = EchoServer::new();
+    let id = server.start("sh", vec![server.script()]).await.unwrap();
+
+    stop_process(&server.registry, &id, Some(Duration::from_secs(5))).unwrap();
+    let mut stages = Vec::new();
+    let info = loop {
+        match server.next_event().await {
+            ProcessEvent::Shutdown { stage } => stages.push(stage),
+            ProcessEvent::Closed(info) => break info,
+            _ => {}
+        }
+    };
+    // The echo server exits on EOF, so no signal is needed
+    assert!(matches!(stages.as_slice(), [ShutdownStage::StdinClosed]));
+    assert_eq!(info.code, Some(0));
+    assert!(info.stop_requested);
+    assert!(server.registry.lock().unwrap().is_empty());
+    assert!(send_to_process(&server.registry, id, "{}".to_string())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn runs_commands_through_the_shell_on_request() {
+    let mut server = EchoServer::new();
+    // `exec` is a shell builtin, so it only runs through `sh -c`
+    let options = ProcessOptions {
+        run_in_shell: true,
+        ..Default::default()
+    };
+    let id = server
+        .start_with("exec sh", vec![server.script()], options)
+        .await
+        .expect("start through the shell");
+
+    let info = server.registry.lock().unwrap()[&id].info(&id);
+    assert_eq!(info.command, "exec sh");
+    assert!(
+        info.resolved_path.ends_with("/sh"),
+        "{}",
+        info.resolved_path
+    );
+
+    server.request(&id, 2, "ping").await;
+    assert_eq!(server.next_message().await["id"], 2);
+
+    stop_process(&server.registry, &id, None).unwrap();
+    server.closed().await;
+}
+
+#[tokio::test]
+async fn reports_missing_commands_before_spawning() {
+    let mut server = EchoServer::new();
+    let err = server
+        .start("daan-no-such-command", Vec::new())
+        .await
+        .unwrap_err();
+    assert!(
+        err.contains("'daan-no-such-command' was not found"),
+        "{}",
+        err
+    );
+    assert!(err.contains("Run in Shell"), "{}", err);
+    assert!(server.registry.lock().unwrap().is_empty());
+}
+
+fn with_limits(limits: ResourceLimits) -> ProcessOptions {
+    ProcessOptions {
+        limits,
+        shutdown_grace_period_ms: Some(200),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn applies_resource_limits() {
+    let mut server = EchoServer::new();
+    let limits = ResourceLimits {
+        max_open_files: Some(64),
+        ..Default::default()
+    };
+    let script = r#"echo "open files: $(ulimit -n)" >&2; exec sleep 30"#;
+    let id = server
+        .start_with(
+            "sh",
+            vec!["-c".to_string(), script.to_string()],
+            with_limits(limits),
+        )
+        .await
+        .unwrap();
+
+    loop {
+        match server.next_event().await {
+            ProcessEvent::Stderr(line) => {
+                assert_eq!(line, "open files: 64");
+                break;
+            }
+            ProcessEvent::Closed(info) => panic!("closed before stderr: {:?}", info),
+            _ => {}
+        }
+    }
+
+    stop_process(&server.registry, &id, None).unwrap();
+    assert_eq!(server.closed().await.limit_exceeded, None);
+}
+
+#[tokio::test]
+async fn shuts_down_after_the_lifetime_limit() {
+    let mut server = EchoServer::new();
+    let limits = ResourceLimits {
+        max_lifetime_ms: Some(300),
+        ..Default::default()
+    };
+    server
+        .start_with("sleep", vec!["30".to_string()], with_limits(limits))
+        .await
+        .unwrap();
+
+    let info = server.closed().await;
+    assert!(!info.stop_requested);
+    assert_eq!(info.limit_exceeded, Some(ResourceLimit::Lifetime));
+}
+
+#[tokio::test]
+async fn reports_the_cpu_time_limit() {
+    let mut server = EchoServer::new();
+    let limits = ResourceLimits {
+        max_cpu_seconds: Some(1),
+        ..Default::default()
+    };
+    server
+        .start_with(
+            "sh",
+            vec!["-c".to_string(), "while :; do :; done".to_string()],
+            with_limits(limits),
+        )
+        .await
+        .unwrap();
+
+    let info = server.closed().await;
+    assert_eq!(info.signal, Some(libc::SIGXCPU));
+    assert_eq!(info.limit_exceeded, Some(ResourceLimit::CpuTime));
+}
+
+#[tokio::test]
+async fn reports_the_cpu_time_limit_when_sigxcpu_is_ignored() {
+    let mut server = EchoServer::new();
+    let limits = ResourceLimits {
+        max_cpu_seconds: Some(1),
+        ..Default::default()
+    };
+    server
+        .start_with(
+            "sh",
+            vec![
+                "-c".to_string(),
+                "trap '' XCPU; while :; do :; done".to_string(),
+            ],
+            with_limits(limits),
+        )
+        .await
+        .unwrap();
+
+    // The hard limit a second later kills it
+    let info = server.closed().await;
+    assert_eq!(info.signal, Some(libc::SIGKILL));
+    assert_eq!(info.limit_exceeded, Some(ResourceLimit::CpuTime));
+}
+
+#[tokio::test]
+async fn rejects_zero_limits() {
+    let mut server = EchoServer::new();
+    let limits = ResourceLimits {
+        max_memory_mb: Some(0),
+        ..Default::default()
+    };
+    let error = server
+        .start_with("sh", vec![server.script()], with_limits(limits))
+        .await
+        .unwrap_err();
+    assert!(error.contains("memory limit"), "{}", error);
+}
+
+// Runs `script` in the sandbox and returns its stderr, or None where the system
+// cannot sandbox (no Landlock, unprivileged user namespaces disabled)
+#[cfg(target_os = "linux")]
+async fn run_sandboxed(script: &str, sandbox: SandboxProfile) -> Option<Vec<String>> {
+    if let Err(e) = SandboxProfile::default().validate() {
+        eprintln!("Skipping sandbox test: {}", e);
+        return None;
+    }
+    let mut server = EchoServer::new();
+    let options = ProcessOptions {
+        sandbox: Some(sandbox),
+        ..Default::default()
+    };
+    match server
+        .start_with("sh", vec!["-c".to_string(), script.to_string()], options)
+        .await
+    {
+        Ok(_) => Some(server.closed().await.stderr_tail),
+        Err(e) if e.contains("Operation not permitted") => {
+            eprintln!("Skipping sandbox test: {}", e);
+            None
+        }
+        Err(e) => panic!("failed to start sandboxed process: {}", e),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn sandbox_confines_writes_to_declared_paths() {
+    let root = std::env::temp_dir().join(format!("daan-sandbox-{}", uuid::Uuid::new_v4()));
+    let (writable, other) = (root.join("writable"), root.join("other"));
+    std::fs::create_dir_all(&writable).unwrap();
+    std::fs::create_dir_all(&other).unwrap();
+    let script = format!(
+        r#"echo ok > {w}/file && echo "writable: yes" >&2
+{{ echo ok > {o}/file; }} 2>/dev/null && echo "other: yes" >&2 || echo "other: no" >&2
+command -v unshare >/dev/null && {{ unshare -U true 2>/dev/null && echo "unshare: yes" >&2; }}
+true"#,
+        w = writable.display(),
+        o = other.display()
+    );
+    let sandbox = SandboxProfile {
+        writable_paths: vec![writable.to_string_lossy().into_owned()],
+        private_tmp: false,
+        ..Default::default()
+    };
+
+    if let Some(stderr) = run_sandboxed(&script, sandbox).await {
+        assert_eq!(stderr, ["writable: yes", "other: no"]);
+        assert!(writable.join("file").exists());
+        assert!(!other.join("file").exists());
+    }
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn sandbox_refuses_namespaces_from_clone_but_not_threads() {
+    // clone(CLONE_NEWUSER | SIGCHLD) through perl, which exits 0 only if it failed;
+    // threads come from clone3, or clone once clone3 is refused
+    let script = format!(
+        r#"if command -v perl >/dev/null; then
+  perl -e 'exit(syscall({}, 0x10000011, 0, 0, 0, 0) < 0 ? 0 : 1)' || echo "clone: allowed" >&2
+fi
+if command -v python3 >/dev/null; then
+  python3 -c 'import threading; t = threading.Thread(target=int); t.start(); t.join()' || echo "thread: failed" >&2
+fi
+true"#,
+        libc::SYS_clone
+    );
+    let sandbox = SandboxProfile {
+        private_tmp: false,
+        ..Default::default()
+    };
+
+    if let Some(stderr) = run_sandboxed(&script, sandbox).await {
+        assert!(stderr.is_empty(), "{:?}", stderr);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn sandbox_mounts_a_private_tmp() {
+    let marker = PathBuf::from("/tmp").join(format!("daan-sandbox-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&marker, "").unwrap();
+    let script = format!(
+        r#"[ -e {} ] || echo "marker: hidden" >&2
+touch /tmp/scratch && echo "tmp: writable" >&2"#,
+        marker.display()
+    );
+
+    if let Some(stderr) = run_sandboxed(&script, SandboxProfile::default()).await {
+        assert_eq!(stderr, ["marker: hidden", "tmp: writable"]);
+    }
+    let _ = std::fs::remove_file(marker);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn sandbox_can_deny_network_access() {
+    let sandbox = SandboxProfile {
+        deny_network: true,
+        ..Default::default()
+    };
+    let ours = std::fs::read_link("/proc/self/ns/net").unwrap();
+    if let Some(stderr) = run_sandboxed("readlink /proc/self/ns/net >&2", sandbox).await {
+        assert_eq!(stderr.len(), 1);
+        assert_ne!(stderr[0], ours.to_string_lossy());
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn rejects_relative_sandbox_paths() {
+    let mut server = EchoServer::new();
+    let options = ProcessOptions {
+        sandbox: Some(SandboxProfile {
+            writable_paths: vec!["data".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let error = server
+        .start_with("sh", vec![server.script()], options)
+        .await
+        .unwrap_err();
+    assert!(error.contains("must be absolute"), "{}", error);
+}
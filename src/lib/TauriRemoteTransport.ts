@@ -0,0 +1,199 @@
+import {
+  Transport,
+  TransportSendOptions,
+} from '@moinfra/mcp-client-sdk/shared/transport.js';
+import { JSONRPCMessage } from '@moinfra/mcp-client-sdk/types.js';
+import { Channel, invoke } from '@tauri-apps/api/core';
+import { isDesktopEnv } from './env';
+import {
+  describeProcessExit,
+  type TauriStdioExitInfo,
+  type TauriStdioProcessEvent,
+  type TauriStdioRestartPolicy,
+} from './TauriStdioTransport';
+
+/** Mirrors `RemoteTransport` in `src-tauri/src/mcp/remote.rs`. */
+export type TauriRemoteTransportKind = 'streamableHttp' | 'sse' | 'websocket';
+
+/** Mirrors `RemoteOptions` in `src-tauri/src/mcp/remote.rs`. */
+export interface TauriRemoteOptions {
+  /** Extra headers sent with every request, e.g. `Authorization`. */
+  headers?: Record<string, string>;
+  /** WebSocket only: interval between keepalive pings. */
+  pingIntervalMs?: number;
+  /** WebSocket only: reconnect policy, defaults to always reconnecting. */
+  reconnect?: TauriStdioRestartPolicy;
+}
+
+/**
+ * Tauri transport for a remote MCP server reached through the backend, which is
+ * not subject to the webview's CORS restrictions. Events arrive on the same
+ * channel type as a stdio process's, with reconnects reported as restarts.
+ */
+export class TauriRemoteTransport implements Transport {
+  onclose?: () => void;
+  onerror?: (error: Error) => void;
+  onmessage?: (message: JSONRPCMessage) => void;
+
+  private connectionId: string | null = null;
+  private kind: TauriRemoteTransportKind;
+  private url: string;
+  private options: TauriRemoteOptions;
+  private channel: Channel<TauriStdioProcessEvent> | null = null;
+  private isClosed = false;
+
+  sessionId?: string;
+
+  /** Number of times the backend re-established the connection. */
+  reconnectCount = 0;
+
+  /** How the connection ended, once it has. */
+  lastExit: TauriStdioExitInfo | null = null;
+
+  /** ID of the backend connection, once started. Stays set after close. */
+  get backendConnectionId(): string | null {
+    return this.connectionId;
+  }
+
+  constructor(
+    kind: TauriRemoteTransportKind,
+    url: string,
+    options: TauriRemoteOptions = {},
+  ) {
+    if (!isDesktopEnv()) {
+      throw new Error(
+        'TauriRemoteTransport can only be used in a Tauri environment.',
+      );
+    }
+    this.kind = kind;
+    this.url = url;
+    this.options = options;
+  }
+
+  async start(): Promise<void> {
+    if (this.connectionId) {
+      throw new Error('Transport already started.');
+    }
+    if (this.isClosed) {
+      throw new Error('Transport has been closed.');
+    }
+
+    // Created before the connection exists, so no event can be missed
+    const channel = new Channel<TauriStdioProcessEvent>();
+    channel.onmessage = (event) => this.handleEvent(event);
+    this.channel = channel;
+
+    try {
+      this.connectionId = await invoke<string>('start_remote_connection', {
+        transport: this.kind,
+        url: this.url,
+        options: this.options,
+        onEvent: channel,
+      });
+      console.log(
+        `Backend opened ${this.kind} connection ${this.connectionId} to ${this.url}`,
+      );
+    } catch (error) {
+      console.error('Failed to open remote connection:', error);
+      const err = new Error(`Failed to connect: ${error}`);
+      this.onerror?.(err);
+      this.cleanup();
+      throw err;
+    }
+  }
+
+  private handleEvent(event: TauriStdioProcessEvent): void {
+    const label = this.connectionId ?? this.url;
+    switch (event.event) {
+      case 'message':
+        try {
+          this.onmessage?.(JSON.parse(event.data) as JSONRPCMessage);
+        } catch (e) {
+          console.error(
+            `Failed to parse JSON message from connection ${label}:`,
+            event.data,
+            e,
+          );
+          this.onerror?.(new Error(`Received non-JSON message: ${event.data}`));
+        }
+        break;
+      case 'error':
+        this.onerror?.(new Error(`Connection error: ${event.data}`));
+        break;
+      case 'restarting':
+        console.warn(
+          `[Connection ${label}] ${event.data.reason}, reconnecting in ${event.data.delayMs}ms (attempt ${event.data.attempt}/${event.data.maxRetries})`,
+        );
+        break;
+      case 'restarted':
+        this.reconnectCount = event.data.restartCount;
+        console.log(`[Connection ${label}] reconnected`);
+        break;
+      case 'closed':
+        console.log(
+          `Connection ${label} closed by backend:`,
+          describeProcessExit(event.data),
+        );
+        this.lastExit = event.data;
+        this.handleClose();
+        break;
+      // Remote connections have no stderr and no shutdown stages
+      default:
+        break;
+    }
+  }
+
+  async send(
+    message: JSONRPCMessage,
+    options?: TransportSendOptions,
+  ): Promise<void> {
+    if (!this.connectionId || this.isClosed) {
+      throw new Error('Transport not started or already closed.');
+    }
+
+    try {
+      await invoke('send_message_to_connection', {
+        id: this.connectionId,
+        message: JSON.stringify(message),
+      });
+    } catch (error) {
+      const err = new Error(`Failed to send message: ${error}`);
+      this.onerror?.(err);
+      throw err;
+    }
+  }
+
+  async close(): Promise<void> {
+    if (this.isClosed) {
+      return;
+    }
+    const id = this.connectionId;
+    this.cleanup();
+    if (id) {
+      try {
+        await invoke('stop_remote_connection', { id });
+      } catch (error) {
+        // The backend may have closed the connection already
+        console.warn(`Failed to stop remote connection ${id}:`, error);
+      }
+    }
+    this.handleClose();
+  }
+
+  private handleClose(): void {
+    if (this.isClosed) {
+      return;
+    }
+    this.isClosed = true;
+    this.cleanup();
+    this.onclose?.();
+  }
+
+  private cleanup(): void {
+    // Events still in flight for this transport are dropped
+    if (this.channel) {
+      this.channel.onmessage = () => {};
+      this.channel = null;
+    }
+  }
+}
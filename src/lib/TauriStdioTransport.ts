@@ -3,10 +3,217 @@ import {
   TransportSendOptions,
 } from '@moinfra/mcp-client-sdk/shared/transport.js';
 import { JSONRPCMessage } from '@moinfra/mcp-client-sdk/types.js';
-import { invoke } from '@tauri-apps/api/core';
-import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import { Channel, invoke } from '@tauri-apps/api/core';
 import { isDesktopEnv } from './env';
 
+/**
+ * Spawn settings forwarded to `start_external_process`.
+ * Mirrors `ProcessOptions` in `src-tauri/src/mcp/control.rs`.
+ */
+export interface TauriStdioProcessOptions {
+  env?: Record<string, string>;
+  /** 'inherit' overlays `env` on Daan's environment, 'clear' uses only `env`. */
+  envPolicy?: 'inherit' | 'clear';
+  cwd?: string;
+  /** Limit for queueing and writing one message to the process's stdin. */
+  writeTimeoutMs?: number;
+  /** Restart the process in the backend when it exits on its own. */
+  restart?: TauriStdioRestartPolicy;
+  /** How messages are delimited on stdin/stdout, defaults to 'ndjson'. */
+  framing?: TauriStdioFraming;
+  /** Larger messages on stdout are dropped with an error. */
+  maxMessageSize?: number;
+  /** Record every message to and from the process to a JSONL file. */
+  recordTraffic?: boolean;
+  /** Directory for recordings, defaults to `mcp-traces` in the app log dir. */
+  recordDir?: string;
+  /** Resource limits applied to the process when it is spawned. */
+  limits?: TauriStdioResourceLimits;
+  /** Confine the process (Linux only); it runs unconfined if unset. */
+  sandbox?: TauriStdioSandboxProfile;
+  /**
+   * Run the command through `sh -c` (`cmd.exe /c` on Windows), for commands
+   * that rely on aliases, functions or pipes.
+   */
+  runInShell?: boolean;
+}
+
+/** Mirrors `SandboxProfile` in `src-tauri/src/mcp/sandbox.rs`. */
+export interface TauriStdioSandboxProfile {
+  /** Absolute paths the process may write to; everything else is read-only. */
+  writablePaths?: string[];
+  /** No network access at all, not even loopback. */
+  denyNetwork?: boolean;
+  /** An empty, writable /tmp of its own; defaults to true. */
+  privateTmp?: boolean;
+}
+
+/** Mirrors `ResourceLimits` in `src-tauri/src/mcp/limits.rs`. */
+export interface TauriStdioResourceLimits {
+  /** Address space, which includes memory reserved but not used. */
+  maxMemoryMb?: number;
+  maxCpuSeconds?: number;
+  maxOpenFiles?: number;
+  /** Counted over all processes of the user, not only the server's. */
+  maxProcesses?: number;
+  /** Wall-clock time a run may last before it is shut down. */
+  maxLifetimeMs?: number;
+}
+
+/** Mirrors `ResourceLimit` in `src-tauri/src/mcp/limits.rs`. */
+export type TauriStdioResourceLimit =
+  | 'memory'
+  | 'cpuTime'
+  | 'openFiles'
+  | 'processes'
+  | 'lifetime';
+
+const RESOURCE_LIMIT_LABELS: Record<TauriStdioResourceLimit, string> = {
+  memory: 'Memory limit',
+  cpuTime: 'CPU time limit',
+  openFiles: 'Open files limit',
+  processes: 'Process limit',
+  lifetime: 'Lifetime limit',
+};
+
+/** Mirrors `FramingMode` in `src-tauri/src/mcp/framing.rs`. */
+export type TauriStdioFraming = 'ndjson' | 'contentLength' | 'auto';
+
+/** Mirrors `ProcessEvent` in `src-tauri/src/mcp/events.rs`. */
+export type TauriStdioProcessEvent =
+  | { event: 'message'; data: string }
+  | { event: 'stderr'; data: string }
+  | { event: 'error'; data: string }
+  | {
+      event: 'restarting';
+      data: {
+        attempt: number;
+        maxRetries: number;
+        delayMs: number;
+        reason: string;
+      };
+    }
+  | { event: 'restarted'; data: { restartCount: number } }
+  | {
+      event: 'shutdown';
+      data: { stage: 'stdinClosed' | 'terminate' | 'kill' };
+    }
+  | { event: 'closed'; data: TauriStdioExitInfo };
+
+/** Mirrors `ExitInfo` in `src-tauri/src/mcp/events.rs`. */
+export interface TauriStdioExitInfo {
+  /** Null if the process was killed by a signal or could not be waited for. */
+  code: number | null;
+  signal: number | null;
+  /** Whether the exit followed `stop_external_process` or Daan quitting. */
+  stopRequested: boolean;
+  uptimeMs: number;
+  /** Last lines written to stderr, oldest first. */
+  stderrTail: string[];
+  /** Resource limit the process ran into, as far as the backend can tell. */
+  limitExceeded: TauriStdioResourceLimit | null;
+  /** Why a remote connection closed; null for processes. */
+  reason: string | null;
+}
+
+/** One-line reason for a process exit, for display in the UI. */
+export function describeProcessExit(info: TauriStdioExitInfo): string {
+  let reason: string;
+  if (info.stopRequested) {
+    reason = 'Stopped';
+  } else if (info.reason !== null) {
+    reason = info.reason;
+  } else if (info.limitExceeded !== null) {
+    reason = `${RESOURCE_LIMIT_LABELS[info.limitExceeded]} exceeded`;
+  } else if (info.signal !== null) {
+    reason = `Killed by signal ${info.signal}`;
+  } else if (info.code !== null) {
+    reason = info.code === 0 ? 'Exited' : `Exited with code ${info.code}`;
+  } else {
+    reason = 'Exited';
+  }
+  reason += ` after ${(info.uptimeMs / 1000).toFixed(1)}s`;
+  const lastStderr = info.stderrTail[info.stderrTail.length - 1];
+  return lastStderr && !info.stopRequested ? `${reason}: ${lastStderr}` : reason;
+}
+
+/** Mirrors `RestartPolicy` in `src-tauri/src/mcp/supervisor.rs`. */
+export interface TauriStdioRestartPolicy {
+  mode: 'never' | 'onFailure' | 'always';
+  maxRetries?: number;
+  initialBackoffMs?: number;
+  maxBackoffMs?: number;
+}
+
+/**
+ * Snapshot of a backend-managed process, as returned by `list_external_processes`.
+ * Mirrors `ProcessInfo` in `src-tauri/src/mcp/control.rs`.
+ */
+export interface ExternalProcessInfo {
+  id: string;
+  command: string;
+  args: string[];
+  /** Executable that was actually started for `command`. */
+  resolvedPath: string;
+  pid: number | null;
+  /** Milliseconds since the Unix epoch. */
+  startedAt: number;
+  state: 'running' | 'restarting' | 'exiting';
+  restartCount: number;
+  /** Stdout lines that were not JSON-RPC, e.g. banners; kept in the log only. */
+  ignoredStdoutLines: number;
+  /** File the traffic is recorded to, if recording. */
+  recordingPath: string | null;
+}
+
+/** Lists every process currently held by the backend `ProcessRegistry`. */
+export async function listExternalProcesses(): Promise<ExternalProcessInfo[]> {
+  return invoke<ExternalProcessInfo[]>('list_external_processes');
+}
+
+/** One retained output line, as returned by `get_process_logs`. */
+export interface ProcessLogLine {
+  /** Milliseconds since the Unix epoch. */
+  timestamp: number;
+  stream: 'stdout' | 'stderr' | 'system';
+  line: string;
+}
+
+/**
+ * Fetches recent output of a backend process. Logs stay available for a while
+ * after the process exits, so this also works for servers that failed to start.
+ */
+export async function getProcessLogs(
+  processId: string,
+  limit?: number,
+): Promise<ProcessLogLine[]> {
+  return invoke<ProcessLogLine[]>('get_process_logs', {
+    id: processId,
+    limit,
+  });
+}
+
+/** Mirrors `RuntimeInfo` in `src-tauri/src/mcp/runtimes.rs`. */
+export interface RuntimeInfo {
+  /** Command name, e.g. 'npx'. */
+  name: string;
+  /** Executable the command resolves to; null if it was not found. */
+  path: string | null;
+  version: string | null;
+  /** Why the runtime is unusable: not found, or no version reported. */
+  error: string | null;
+}
+
+/**
+ * Checks which common server runtimes (node/npx, python/uv/uvx, deno, bun,
+ * docker) are available, resolved the same way a server command would be.
+ */
+export async function detectRuntimes(
+  options?: TauriStdioProcessOptions,
+): Promise<RuntimeInfo[]> {
+  return invoke<RuntimeInfo[]>('detect_available_runtimes', { options });
+}
+
 /**
  * Tauri transport for stdio communication with a backend-managed process.
  */
@@ -14,17 +221,39 @@ export class TauriStdioTransport implements Transport {
   onclose?: () => void;
   onerror?: (error: Error) => void;
   onmessage?: (message: JSONRPCMessage) => void;
+  /** Called once the backend reports that the process is gone for good. */
+  onexit?: (info: TauriStdioExitInfo) => void;
 
   private processId: string | null = null;
   private command: string;
   private args: string[];
-  private unlisteners: UnlistenFn[] = [];
+  private options: TauriStdioProcessOptions;
+  private channel: Channel<TauriStdioProcessEvent> | null = null;
   private isClosed = false; // Flag to prevent actions after close
+  private attachProcessId: string | null = null; // Set when reusing a running process
 
   // We don't implement sessionId here, but could if needed.
   sessionId?: string;
 
-  constructor(command: string, args: string[] = []) {
+  /** Number of times the backend restarted the process under the same ID. */
+  restartCount = 0;
+
+  /** How the process ended, once it has. */
+  lastExit: TauriStdioExitInfo | null = null;
+
+  /** Executable the backend resolved the command to, once started. */
+  resolvedPath: string | null = null;
+
+  /** ID of the backend process, once started. Stays set after close. */
+  get backendProcessId(): string | null {
+    return this.processId;
+  }
+
+  constructor(
+    command: string,
+    args: string[] = [],
+    options: TauriStdioProcessOptions = {},
+  ) {
     if (!isDesktopEnv()) {
       throw new Error(
         'TauriStdioTransport can only be used in a Tauri environment.',
@@ -32,6 +261,29 @@ export class TauriStdioTransport implements Transport {
     }
     this.command = command;
     this.args = args;
+    this.options = options;
+  }
+
+  /**
+   * Creates a transport for a process that is already running in the backend,
+   * e.g. one started before the webview was reloaded. `start()` will only
+   * subscribe to its events instead of spawning a new process.
+   */
+  static async attach(processId: string): Promise<TauriStdioTransport> {
+    const info = (await listExternalProcesses()).find(
+      (p) => p.id === processId,
+    );
+    if (!info) {
+      throw new Error(`Process ${processId} is not managed by the backend.`);
+    }
+    if (info.state !== 'running') {
+      throw new Error(`Process ${processId} is ${info.state}.`);
+    }
+    const transport = new TauriStdioTransport(info.command, info.args);
+    transport.attachProcessId = info.id;
+    transport.restartCount = info.restartCount;
+    transport.resolvedPath = info.resolvedPath;
+    return transport;
   }
 
   async start(): Promise<void> {
@@ -42,6 +294,28 @@ export class TauriStdioTransport implements Transport {
       throw new Error('Transport has been closed.');
     }
 
+    // Created before the process exists, so no event can be missed
+    const channel = new Channel<TauriStdioProcessEvent>();
+    channel.onmessage = (event) => this.handleEvent(event);
+    this.channel = channel;
+
+    if (this.attachProcessId) {
+      this.processId = this.attachProcessId;
+      console.log(`Reattaching to backend process ${this.processId}`);
+      try {
+        await invoke('attach_process_events', {
+          id: this.processId,
+          onEvent: channel,
+        });
+      } catch (error) {
+        const err = new Error(`Failed to attach to process: ${error}`);
+        this.onerror?.(err);
+        await this.cleanup();
+        throw err;
+      }
+      return;
+    }
+
     console.log(
       `Requesting backend to start process: ${this.command} ${this.args.join(' ')}`,
     );
@@ -50,11 +324,16 @@ export class TauriStdioTransport implements Transport {
       const pid = await invoke<string>('start_external_process', {
         command: this.command,
         args: this.args,
+        options: this.options,
+        onEvent: channel,
       });
       this.processId = pid;
       console.log(`Backend started process with ID: ${this.processId}`);
-      await this.setupEventListeners();
-      // Maybe emit a custom "started" event or resolve promise here
+      // Only informational, so a failed lookup does not fail the start
+      const info = (await listExternalProcesses().catch(() => [])).find(
+        (p) => p.id === pid,
+      );
+      this.resolvedPath = info?.resolvedPath ?? null;
     } catch (error) {
       console.error('Failed to start external process:', error);
       const err = new Error(`Failed to start process: ${error}`);
@@ -64,76 +343,58 @@ export class TauriStdioTransport implements Transport {
     }
   }
 
-  private async setupEventListeners(): Promise<void> {
-    if (!this.processId) return;
-
-    const messageEvent = `process_message_${this.processId}`;
-    const errorEvent = `process_error_${this.processId}`;
-    const stderrEvent = `process_stderr_${this.processId}`; // Listen to stderr for debugging
-    const closeEvent = `process_closed_${this.processId}`;
-
-    try {
-      const unlistenMessage = await listen<string>(messageEvent, (event) => {
+  private handleEvent(event: TauriStdioProcessEvent): void {
+    const label = this.processId ?? this.command;
+    switch (event.event) {
+      case 'message':
         console.debug(
-          `Received raw message from backend (${this.processId}):`,
-          event.payload,
+          `Received raw message from backend (${label}):`,
+          event.data,
         );
         try {
-          const message = JSON.parse(event.payload) as JSONRPCMessage;
-          this.onmessage?.(message);
+          this.onmessage?.(JSON.parse(event.data) as JSONRPCMessage);
         } catch (e) {
           console.error(
-            `Failed to parse JSON message from process ${this.processId}:`,
-            event.payload,
+            `Failed to parse JSON message from process ${label}:`,
+            event.data,
             e,
           );
-          const err = new Error(`Received non-JSON message: ${event.payload}`);
-          this.onerror?.(err);
+          this.onerror?.(new Error(`Received non-JSON message: ${event.data}`));
         }
-      });
-
-      const unlistenError = await listen<string>(errorEvent, (event) => {
+        break;
+      case 'error':
         console.error(
-          `Received error event from backend (${this.processId}):`,
-          event.payload,
+          `Received error event from backend (${label}):`,
+          event.data,
         );
-        const err = new Error(`Process error: ${event.payload}`);
-        this.onerror?.(err);
-        // Consider if an error should trigger closure
-        // this.close();
-      });
-
-      const unlistenStderr = await listen<string>(stderrEvent, (event) => {
-        console.warn(`[Process ${this.processId} stderr]:`, event.payload);
-        // Optionally trigger onerror for stderr messages if desired
-        // const err = new Error(`Process stderr: ${event.payload}`);
-        // this.onerror?.(err);
-      });
-
-      const unlistenClose = await listen<string>(closeEvent, (event) => {
+        this.onerror?.(new Error(`Process error: ${event.data}`));
+        break;
+      case 'stderr':
+        console.warn(`[Process ${label} stderr]:`, event.data);
+        break;
+      case 'shutdown':
+        console.log(`[Process ${label}] shutdown stage:`, event.data.stage);
+        break;
+      case 'restarting':
+        console.warn(
+          `[Process ${label}] ${event.data.reason}, restarting in ${event.data.delayMs}ms (attempt ${event.data.attempt}/${event.data.maxRetries})`,
+        );
+        break;
+      case 'restarted':
+        this.restartCount = event.data.restartCount;
+        console.log(
+          `[Process ${label}] restarted (${event.data.restartCount} restarts so far)`,
+        );
+        break;
+      case 'closed':
         console.log(
-          `Received close event from backend (${this.processId}):`,
-          event.payload,
+          `Received close event from backend (${label}):`,
+          event.data,
         );
+        this.lastExit = event.data;
+        this.onexit?.(event.data);
         this.handleClose(); // Call internal close handler
-      });
-
-      this.unlisteners = [
-        unlistenMessage,
-        unlistenError,
-        unlistenStderr,
-        unlistenClose,
-      ];
-      console.log(`Event listeners setup for process ${this.processId}`);
-    } catch (error) {
-      console.error(
-        `Failed to set up event listeners for process ${this.processId}:`,
-        error,
-      );
-      const err = new Error(`Failed to listen to backend events: ${error}`);
-      this.onerror?.(err);
-      await this.close(); // Close if listeners can't be set up
-      throw err;
+        break;
     }
   }
 
@@ -208,8 +469,11 @@ export class TauriStdioTransport implements Transport {
 
   private async cleanup(): Promise<void> {
     console.log(`Cleaning up resources for process ${this.processId}`);
-    this.unlisteners.forEach((unlisten) => unlisten());
-    this.unlisteners = [];
+    // Events still in flight for this transport are dropped
+    if (this.channel) {
+      this.channel.onmessage = () => {};
+      this.channel = null;
+    }
     // Don't nullify processId here, it might be needed for the final stop command
     // this.processId = null;
   }
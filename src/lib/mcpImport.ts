@@ -0,0 +1,76 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** Mirrors `ConfigClient` in `src-tauri/src/mcp/import.rs`. */
+export type McpImportClient =
+  | 'claudeDesktop'
+  | 'cursor'
+  | 'windsurf'
+  | 'vsCode'
+  | 'file';
+
+export const MCP_IMPORT_CLIENT_LABELS: Record<McpImportClient, string> = {
+  claudeDesktop: 'Claude Desktop',
+  cursor: 'Cursor',
+  windsurf: 'Windsurf',
+  vsCode: 'VS Code',
+  file: 'config file',
+};
+
+/** Mirrors `ImportedConfig` in `src-tauri/src/mcp/import.rs`. */
+export type McpImportedConfig =
+  | {
+      type: 'stdio';
+      command: string;
+      args: string[];
+      env: Record<string, string>;
+      cwd?: string;
+    }
+  | { type: 'sse'; url: string; headers: Record<string, string> }
+  | { type: 'streamableHttp'; url: string; headers: Record<string, string> }
+  | { type: 'websocket'; url: string; headers: Record<string, string> };
+
+export interface McpImportedServer {
+  name: string;
+  client: McpImportClient;
+  /** Path of the config file the server came from. */
+  source: string;
+  config: McpImportedConfig;
+  /** Things that did not carry over exactly, e.g. unresolved placeholders. */
+  notes: string[];
+}
+
+/** Mirrors `ImportReport` in `src-tauri/src/mcp/import.rs`. */
+export interface McpImportReport {
+  sources: {
+    client: McpImportClient;
+    path: string;
+    found: boolean;
+    error: string | null;
+  }[];
+  servers: McpImportedServer[];
+  /** Entries matching a server Daan already has, or one imported before it. */
+  duplicates: {
+    name: string;
+    client: McpImportClient;
+    source: string;
+    duplicateOf: string;
+  }[];
+  unsupported: {
+    name: string;
+    client: McpImportClient;
+    source: string;
+    reason: string;
+  }[];
+}
+
+/**
+ * Reads the MCP servers configured in other clients, plus the `.vscode/mcp.json`
+ * of each workspace directory in `paths`. Nothing is added by this call;
+ * `existing` servers are only used to report duplicates.
+ */
+export async function importMcpServers(
+  paths: string[] = [],
+  existing: ({ name: string } & McpImportedConfig)[] = [],
+): Promise<McpImportReport> {
+  return invoke<McpImportReport>('import_mcp_servers', { paths, existing });
+}
@@ -28,13 +28,19 @@ import {
 import { Switch } from '@/components/ui/Switch';
 import { Textarea } from '@/components/ui/Textarea';
 import { isDesktopEnv } from '@/lib/env';
+import {
+  detectRuntimes,
+  RuntimeInfo,
+  TauriStdioResourceLimits,
+} from '@/lib/TauriStdioTransport';
 import { cn } from '@/lib/utils';
 import { McpServerConfig } from '@/store/mcp'; // Import specific type if needed
 import { miniappsDefinitionAtom } from '@/store/miniapp'; // Import definitions atom
 import { zodResolver } from '@hookform/resolvers/zod';
 import { useAtomValue } from 'jotai'; // Import useAtomValue
-import React, { useEffect, useMemo } from 'react';
+import React, { useEffect, useMemo, useState } from 'react';
 import { useForm } from 'react-hook-form';
+import { useDebounce } from 'use-debounce';
 import { McpServerFormData, mcpServerFormSchema } from './schema';
 
 interface McpServerFormProps {
@@ -51,10 +57,127 @@ const formInitialState: () => Partial<McpServerFormData> = () => ({
   url: '',
   command: undefined,
   args: [],
+  env: {},
+  headers: {},
+  envPolicy: 'inherit',
+  cwd: undefined,
+  restart: undefined,
+  framing: undefined,
+  recordTraffic: undefined,
+  limits: undefined,
+  sandbox: undefined,
+  runInShell: undefined,
   targetMiniappId: undefined,
   autoApproveTools: false,
 });
 
+const formatPairs = (pairs: Record<string, string> | undefined, sep: string) =>
+  Object.entries(pairs ?? {})
+    .map(([key, value]) => `${key}${sep}${value}`)
+    .join('\n');
+
+const parsePairs = (text: string, sep: string): Record<string, string> =>
+  Object.fromEntries(
+    text
+      .split('\n')
+      .map((line) => line.trim())
+      .filter((line) => line.includes(sep))
+      .map((line) => {
+        const idx = line.indexOf(sep);
+        return [line.slice(0, idx).trim(), line.slice(idx + 1)];
+      })
+      .filter(([key]) => key),
+  );
+
+// What to install when a server's runtime is missing
+const RUNTIME_PACKAGES: Record<string, string> = {
+  node: 'Node.js',
+  npx: 'Node.js',
+  python: 'Python',
+  python3: 'Python',
+  uv: 'uv',
+  uvx: 'uv',
+  deno: 'Deno',
+  bun: 'Bun',
+  docker: 'Docker',
+};
+
+// Number inputs for `limits`; `scale` converts the unit shown to the one stored
+const RESOURCE_LIMIT_FIELDS: {
+  key: keyof TauriStdioResourceLimits;
+  label: string;
+  scale: number;
+}[] = [
+  { key: 'maxMemoryMb', label: 'Memory (MB)', scale: 1 },
+  { key: 'maxCpuSeconds', label: 'CPU Time (seconds)', scale: 1 },
+  { key: 'maxOpenFiles', label: 'Open Files', scale: 1 },
+  { key: 'maxProcesses', label: 'Processes', scale: 1 },
+  { key: 'maxLifetimeMs', label: 'Lifetime (minutes)', scale: 60_000 },
+];
+
+// An empty input clears the limit; no limits at all is stored as undefined
+const setResourceLimit = (
+  limits: TauriStdioResourceLimits | undefined,
+  key: keyof TauriStdioResourceLimits,
+  text: string,
+  scale: number,
+): TauriStdioResourceLimits | undefined => {
+  const value = Number(text);
+  const next = {
+    ...limits,
+    [key]: text.trim() && value > 0 ? Math.round(value * scale) : undefined,
+  };
+  return Object.values(next).some((limit) => limit !== undefined)
+    ? next
+    : undefined;
+};
+
+// Matches a bare command like `npx` (not a path) against the detected runtimes
+const findRuntime = (
+  command: string | undefined,
+  runtimes: RuntimeInfo[] | null,
+): RuntimeInfo | undefined => {
+  const name = command
+    ?.trim()
+    .toLowerCase()
+    .replace(/\.(exe|cmd|bat)$/, '');
+  if (!name || /[\\/]/.test(name)) return undefined;
+  return runtimes?.find((runtime) => runtime.name === name);
+};
+
+// One `key<separator>value` pair per line, e.g. env vars or HTTP headers.
+// Keeps the raw text locally so half-typed lines (no separator yet) are not dropped.
+const KeyValueTextarea: React.FC<{
+  value?: Record<string, string>;
+  onChange: (pairs: Record<string, string>) => void;
+  separator: string;
+  placeholder?: string;
+}> = ({ value, onChange, separator, placeholder }) => {
+  const [text, setText] = useState(() => formatPairs(value, separator));
+
+  useEffect(() => {
+    // Resync when the form is reset from outside, e.g. editing another server
+    if (
+      JSON.stringify(parsePairs(text, separator)) !==
+      JSON.stringify(value ?? {})
+    ) {
+      setText(formatPairs(value, separator));
+    }
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [value]);
+
+  return (
+    <Textarea
+      placeholder={placeholder}
+      value={text}
+      onChange={(e) => {
+        setText(e.target.value);
+        onChange(parsePairs(e.target.value, separator));
+      }}
+    />
+  );
+};
+
 export const McpServerForm: React.FC<McpServerFormProps> = ({
   isOpen,
   onOpenChange,
@@ -90,6 +213,36 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
   console.log('form', form);
 
   const serverType = form.watch('type');
+  const command = form.watch('command');
+  const runInShell = form.watch('runInShell');
+  const env = form.watch('env');
+  const envPolicy = form.watch('envPolicy');
+  const cwd = form.watch('cwd');
+
+  // Detected with the server's env and cwd, so the form can warn before it
+  // fails to start; serialised and debounced to not re-run on every keystroke
+  const [detectionOptions] = useDebounce(
+    JSON.stringify({ env, envPolicy, cwd: cwd?.trim() || undefined }),
+    300,
+  );
+  const [runtimes, setRuntimes] = useState<RuntimeInfo[] | null>(null);
+  useEffect(() => {
+    if (!isOpen || serverType !== 'stdio' || !canUseStdio) return;
+    let cancelled = false;
+    detectRuntimes(JSON.parse(detectionOptions))
+      .then((detected) => {
+        if (!cancelled) setRuntimes(detected);
+      })
+      .catch((error) => {
+        // An invalid cwd is reported on submit, don't warn from stale results
+        console.error('Failed to detect runtimes:', error);
+        if (!cancelled) setRuntimes(null);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [isOpen, serverType, canUseStdio, detectionOptions]);
+  const runtime = runInShell ? undefined : findRuntime(command, runtimes);
 
   // Reset form logic
   useEffect(() => {
@@ -103,10 +256,44 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
           type: editingServer.type,
           autoApproveTools: editingServer.autoApproveTools,
           // Set type-specific fields
-          url: editingServer.type === 'sse' ? editingServer.url : undefined,
+          url:
+            editingServer.type === 'sse' ||
+            editingServer.type === 'streamableHttp' ||
+            editingServer.type === 'websocket'
+              ? editingServer.url
+              : undefined,
+          headers:
+            editingServer.type === 'sse' ||
+            editingServer.type === 'streamableHttp' ||
+            editingServer.type === 'websocket'
+              ? (editingServer.headers ?? {})
+              : {},
           command:
             editingServer.type === 'stdio' ? editingServer.command : undefined,
           args: editingServer.type === 'stdio' ? editingServer.args : undefined,
+          env:
+            editingServer.type === 'stdio' ? (editingServer.env ?? {}) : {},
+          envPolicy:
+            editingServer.type === 'stdio'
+              ? (editingServer.envPolicy ?? 'inherit')
+              : 'inherit',
+          cwd: editingServer.type === 'stdio' ? editingServer.cwd : undefined,
+          restart:
+            editingServer.type === 'stdio' ? editingServer.restart : undefined,
+          framing:
+            editingServer.type === 'stdio' ? editingServer.framing : undefined,
+          recordTraffic:
+            editingServer.type === 'stdio'
+              ? editingServer.recordTraffic
+              : undefined,
+          limits:
+            editingServer.type === 'stdio' ? editingServer.limits : undefined,
+          sandbox:
+            editingServer.type === 'stdio' ? editingServer.sandbox : undefined,
+          runInShell:
+            editingServer.type === 'stdio'
+              ? editingServer.runInShell
+              : undefined,
           targetMiniappId:
             editingServer.type === 'miniapp'
               ? editingServer.targetMiniappId
@@ -122,10 +309,28 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
 
   const handleFormSubmit = (data: McpServerFormData) => {
     let finalData: any = { ...data };
-    if (data.type !== 'sse') finalData.url = undefined;
+    const isRemote =
+      data.type === 'sse' ||
+      data.type === 'streamableHttp' ||
+      data.type === 'websocket';
+    if (!isRemote) {
+      finalData.url = undefined;
+      finalData.headers = undefined;
+    }
     if (data.type !== 'stdio') {
       finalData.command = undefined;
       finalData.args = undefined;
+      finalData.env = undefined;
+      finalData.envPolicy = undefined;
+      finalData.cwd = undefined;
+      finalData.restart = undefined;
+      finalData.framing = undefined;
+      finalData.recordTraffic = undefined;
+      finalData.limits = undefined;
+      finalData.sandbox = undefined;
+      finalData.runInShell = undefined;
+    } else if (!data.cwd?.trim()) {
+      finalData.cwd = undefined;
     }
     if (data.type !== 'miniapp') {
       finalData.targetMiniappId = undefined;
@@ -245,6 +450,16 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
                     <SelectItem value="sse">
                       SSE Client (External URL)
                     </SelectItem>
+                    {canUseStdio && (
+                      <SelectItem value="streamableHttp">
+                        Streamable HTTP (External URL)
+                      </SelectItem>
+                    )}
+                    {canUseStdio && (
+                      <SelectItem value="websocket">
+                        WebSocket (External URL)
+                      </SelectItem>
+                    )}
                     {canUseStdio && (
                       <SelectItem value="stdio">
                         Stdio (Local Command)
@@ -263,8 +478,10 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
             )}
           />
 
-          {/* SSE URL Input */}
-          {serverType === 'sse' && (
+          {/* SSE / Streamable HTTP / WebSocket URL Input */}
+          {(serverType === 'sse' ||
+            serverType === 'streamableHttp' ||
+            serverType === 'websocket') && (
             <FormField
               control={form.control}
               name="url"
@@ -273,13 +490,21 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
                   <FormLabel>Server URL</FormLabel>
                   <FormControl>
                     <Input
-                      placeholder="http://localhost:8080/mcp"
+                      placeholder={
+                        serverType === 'websocket'
+                          ? 'ws://localhost:8080/mcp'
+                          : 'http://localhost:8080/mcp'
+                      }
                       {...field}
                       value={field.value ?? ''} // Handle potential undefined
                     />
                   </FormControl>
                   <FormDescription>
-                    The URL endpoint for the MCP SSE connection.
+                    {serverType === 'websocket'
+                      ? 'The ws:// or wss:// endpoint of the MCP server.'
+                      : serverType === 'streamableHttp'
+                        ? 'The MCP endpoint of the server, e.g. https://example.com/mcp.'
+                        : 'The URL endpoint for the MCP SSE connection.'}
                   </FormDescription>
                   <FormMessage />
                 </FormItem>
@@ -287,6 +512,38 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
             />
           )}
 
+          {/* Remote Server Headers Input; only the backend can send them */}
+          {(serverType === 'sse' ||
+            serverType === 'streamableHttp' ||
+            serverType === 'websocket') &&
+            canUseStdio && (
+              <FormField
+                control={form.control}
+                name="headers"
+                render={({ field }) => (
+                  <FormItem>
+                    <FormLabel>Headers (Optional)</FormLabel>
+                    <FormControl>
+                      <KeyValueTextarea
+                        separator=":"
+                        placeholder="Authorization: Bearer ..."
+                        value={
+                          field.value as Record<string, string> | undefined
+                        }
+                        onChange={field.onChange}
+                      />
+                    </FormControl>
+                    <FormDescription>
+                      {serverType === 'websocket'
+                        ? 'One Name: value pair per line, sent with the handshake.'
+                        : 'One Name: value pair per line, sent with every request.'}
+                    </FormDescription>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+            )}
+
           {/* Stdio Command Input */}
           {serverType === 'stdio' && canUseStdio && (
             <FormField
@@ -306,6 +563,22 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
                     The command or executable to run (must be in PATH or
                     absolute).
                   </FormDescription>
+                  {runtime && !runtime.path && (
+                    <p className="text-sm text-amber-600 dark:text-amber-400">
+                      {runtime.name} was not found. Install{' '}
+                      {RUNTIME_PACKAGES[runtime.name]} or use an absolute path.
+                    </p>
+                  )}
+                  {runtime?.path && runtime.error && (
+                    <p className="text-sm text-amber-600 dark:text-amber-400">
+                      {runtime.path} does not work: {runtime.error}
+                    </p>
+                  )}
+                  {runtime?.path && runtime.version && (
+                    <p className="text-muted-foreground text-sm">
+                      Using {runtime.name} {runtime.version} at {runtime.path}
+                    </p>
+                  )}
                   <FormMessage />
                 </FormItem>
               )}
@@ -347,6 +620,339 @@ export const McpServerForm: React.FC<McpServerFormProps> = ({
               )}
             />
           )}
+          {/* Stdio Working Directory Input */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="cwd"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Working Directory (Optional)</FormLabel>
+                  <FormControl>
+                    <Input
+                      placeholder="/path/to/project"
+                      {...field}
+                      value={field.value ?? ''}
+                    />
+                  </FormControl>
+                  <FormDescription>
+                    Directory the command is started in. Defaults to Daan's
+                    working directory.
+                  </FormDescription>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Environment Variables Input */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="env"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Environment Variables (Optional)</FormLabel>
+                  <FormControl>
+                    <KeyValueTextarea
+                      separator="="
+                      placeholder="API_TOKEN=...&#10;NODE_OPTIONS=--max-old-space-size=4096"
+                      value={field.value as Record<string, string> | undefined}
+                      onChange={field.onChange}
+                    />
+                  </FormControl>
+                  <FormDescription>
+                    One KEY=VALUE pair per line.
+                  </FormDescription>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Inherit Environment Switch */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="envPolicy"
+              render={({ field }) => (
+                <FormItem className="flex flex-row items-center justify-between rounded-lg border p-3 shadow-sm">
+                  <div className="space-y-0.5">
+                    <FormLabel>Inherit Environment</FormLabel>
+                    <FormDescription>
+                      Start from Daan's environment. When off, only the
+                      variables above are passed to the command.
+                    </FormDescription>
+                  </div>
+                  <FormControl>
+                    <Switch
+                      checked={field.value !== 'clear'}
+                      onCheckedChange={(checked) =>
+                        field.onChange(checked ? 'inherit' : 'clear')
+                      }
+                    />
+                  </FormControl>
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Restart Policy Selector */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="restart"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Restart Policy</FormLabel>
+                  <Select
+                    onValueChange={(mode) =>
+                      field.onChange(
+                        mode === 'never' ? undefined : { ...field.value, mode },
+                      )
+                    }
+                    value={field.value?.mode ?? 'never'}
+                  >
+                    <FormControl>
+                      <SelectTrigger>
+                        <SelectValue />
+                      </SelectTrigger>
+                    </FormControl>
+                    <SelectContent>
+                      <SelectItem value="never">Never</SelectItem>
+                      <SelectItem value="onFailure">On failure</SelectItem>
+                      <SelectItem value="always">Always</SelectItem>
+                    </SelectContent>
+                  </Select>
+                  <FormDescription>
+                    Restart the command when it exits on its own, with
+                    exponential backoff.
+                  </FormDescription>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Message Framing Selector */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="framing"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Message Framing</FormLabel>
+                  <Select
+                    onValueChange={(framing) =>
+                      field.onChange(framing === 'ndjson' ? undefined : framing)
+                    }
+                    value={field.value ?? 'ndjson'}
+                  >
+                    <FormControl>
+                      <SelectTrigger>
+                        <SelectValue />
+                      </SelectTrigger>
+                    </FormControl>
+                    <SelectContent>
+                      <SelectItem value="ndjson">
+                        Newline-delimited JSON
+                      </SelectItem>
+                      <SelectItem value="contentLength">
+                        Content-Length headers
+                      </SelectItem>
+                      <SelectItem value="auto">Auto-detect</SelectItem>
+                    </SelectContent>
+                  </Select>
+                  <FormDescription>
+                    How the command delimits messages. Most MCP servers use
+                    one JSON message per line.
+                  </FormDescription>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Run in Shell Switch */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="runInShell"
+              render={({ field }) => (
+                <FormItem className="flex flex-row items-center justify-between rounded-lg border p-3 shadow-sm">
+                  <div className="space-y-0.5">
+                    <FormLabel>Run in Shell</FormLabel>
+                    <FormDescription>
+                      Pass the command to the system shell as written, for
+                      aliases, pipes or shell builtins. Otherwise it is looked
+                      up in PATH, the login shell's PATH and nvm, asdf or pyenv
+                      shims.
+                    </FormDescription>
+                  </div>
+                  <FormControl>
+                    <Switch
+                      checked={field.value ?? false}
+                      onCheckedChange={(checked) =>
+                        field.onChange(checked || undefined)
+                      }
+                    />
+                  </FormControl>
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Traffic Recording Switch */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="recordTraffic"
+              render={({ field }) => (
+                <FormItem className="flex flex-row items-center justify-between rounded-lg border p-3 shadow-sm">
+                  <div className="space-y-0.5">
+                    <FormLabel>Record Traffic</FormLabel>
+                    <FormDescription>
+                      Write every JSON-RPC message to a JSONL file in the app's
+                      log folder, for attaching to bug reports.
+                    </FormDescription>
+                  </div>
+                  <FormControl>
+                    <Switch
+                      checked={field.value ?? false}
+                      onCheckedChange={(checked) =>
+                        field.onChange(checked || undefined)
+                      }
+                    />
+                  </FormControl>
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Resource Limits */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="limits"
+              render={({ field }) => (
+                <FormItem className="space-y-3 rounded-lg border p-3 shadow-sm">
+                  <div className="space-y-0.5">
+                    <FormLabel>Resource Limits (Optional)</FormLabel>
+                    <FormDescription>
+                      Leave a field empty to keep Daan's own limit. Only the
+                      lifetime is enforced on Windows.
+                    </FormDescription>
+                  </div>
+                  <div className="grid grid-cols-2 gap-3">
+                    {RESOURCE_LIMIT_FIELDS.map(({ key, label, scale }) => {
+                      const limit = field.value?.[key];
+                      return (
+                        <div key={key} className="space-y-1">
+                          <FormLabel className="text-xs">{label}</FormLabel>
+                          <Input
+                            type="number"
+                            min={1}
+                            value={limit !== undefined ? limit / scale : ''}
+                            onChange={(e) =>
+                              field.onChange(
+                                setResourceLimit(
+                                  field.value,
+                                  key,
+                                  e.target.value,
+                                  scale,
+                                ),
+                              )
+                            }
+                          />
+                        </div>
+                      );
+                    })}
+                  </div>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+
+          {/* Stdio Sandbox Settings */}
+          {serverType === 'stdio' && canUseStdio && (
+            <FormField
+              control={form.control}
+              name="sandbox"
+              render={({ field }) => (
+                <FormItem className="space-y-3 rounded-lg border p-3 shadow-sm">
+                  <div className="flex flex-row items-center justify-between">
+                    <div className="space-y-0.5">
+                      <FormLabel>Sandbox</FormLabel>
+                      <FormDescription>
+                        Run the command with a read-only filesystem and a
+                        private /tmp (Linux only). Recommended for servers you
+                        don't fully trust.
+                      </FormDescription>
+                    </div>
+                    <FormControl>
+                      <Switch
+                        checked={field.value !== undefined}
+                        onCheckedChange={(checked) =>
+                          field.onChange(checked ? {} : undefined)
+                        }
+                      />
+                    </FormControl>
+                  </div>
+                  {field.value && (
+                    <>
+                      <Textarea
+                        placeholder="/home/me/notes&#10;/home/me/.cache/server"
+                        value={field.value.writablePaths?.join('\n') ?? ''}
+                        onChange={(e) =>
+                          field.onChange({
+                            ...field.value,
+                            writablePaths: e.target.value
+                              .split('\n')
+                              .map((s) => s.trim())
+                              .filter(Boolean),
+                          })
+                        }
+                      />
+                      <FormDescription>
+                        Absolute paths the command may write to, one per line.
+                      </FormDescription>
+                      <div className="flex flex-row items-center justify-between">
+                        <FormLabel>Private /tmp</FormLabel>
+                        <Switch
+                          checked={field.value.privateTmp ?? true}
+                          onCheckedChange={(checked) =>
+                            field.onChange({
+                              ...field.value,
+                              privateTmp: checked ? undefined : false,
+                            })
+                          }
+                        />
+                      </div>
+                      <FormDescription>
+                        Private /tmp and Block Network need user namespaces,
+                        which some systems do not allow.
+                      </FormDescription>
+                      <div className="flex flex-row items-center justify-between">
+                        <FormLabel>Block Network</FormLabel>
+                        <Switch
+                          checked={field.value.denyNetwork ?? false}
+                          onCheckedChange={(checked) =>
+                            field.onChange({
+                              ...field.value,
+                              denyNetwork: checked || undefined,
+                            })
+                          }
+                        />
+                      </div>
+                    </>
+                  )}
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+
           {/* === NEW: Miniapp Target Selector === */}
           {serverType === 'miniapp' && (
             <FormField
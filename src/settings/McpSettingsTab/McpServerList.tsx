@@ -1,4 +1,9 @@
 import { Button } from '@/components/ui/Button';
+import {
+  Popover,
+  PopoverContent,
+  PopoverTrigger,
+} from '@/components/ui/Popover';
 import { Switch } from '@/components/ui/Switch';
 import {
   Table,
@@ -13,11 +18,13 @@ import {
   TooltipContent,
   TooltipTrigger,
 } from '@/components/ui/Tooltip';
+import { getProcessLogs, ProcessLogLine } from '@/lib/TauriStdioTransport';
 import { cn } from '@/lib/utils'; // Adjust import path
 import { McpServerConfig, McpServerState } from '@/store/mcp'; // Adjust import path
-import React from 'react';
+import React, { useState } from 'react';
 import {
   LuCircleDotDashed,
+  LuFileText,
   LuInfo,
   LuPencil, // Using Pencil for Edit for clarity
   LuPlug,
@@ -66,6 +73,49 @@ const getConnectionStatus = (state: McpServerState | undefined) => {
   };
 };
 
+// Shows the output the backend retained for a stdio server's process
+const ProcessLogsButton: React.FC<{ processId: string }> = ({ processId }) => {
+  const [lines, setLines] = useState<ProcessLogLine[] | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const loadLogs = async () => {
+    try {
+      setLines(await getProcessLogs(processId, 200));
+      setError(null);
+    } catch (e) {
+      setLines(null);
+      setError(String(e));
+    }
+  };
+
+  return (
+    <Popover onOpenChange={(open) => open && loadLogs()}>
+      <PopoverTrigger asChild>
+        <Button variant="ghost" size="xs" title="Show Process Logs">
+          <LuFileText className="h-4 w-4" />
+        </Button>
+      </PopoverTrigger>
+      <PopoverContent className="w-[32rem] p-2" align="end">
+        <pre className="max-h-80 overflow-auto font-mono text-xs whitespace-pre-wrap">
+          {error && <span className="text-red-500">{error}</span>}
+          {lines?.length === 0 && 'No output captured.'}
+          {lines?.map((entry, i) => (
+            <div
+              key={i}
+              className={cn(
+                entry.stream === 'stderr' && 'text-red-500',
+                entry.stream === 'system' && 'text-muted-foreground italic',
+              )}
+            >
+              {entry.line}
+            </div>
+          ))}
+        </pre>
+      </PopoverContent>
+    </Popover>
+  );
+};
+
 interface McpServerListProps {
   servers: McpServerConfig[];
   serverStates: Map<string, McpServerState>;
@@ -101,20 +151,34 @@ export const McpServerList: React.FC<McpServerListProps> = ({
         return 'Built-in';
       case 'sse':
         return 'SSE';
+      case 'streamableHttp':
+        return 'Streamable HTTP';
+      case 'websocket':
+        return 'WebSocket';
       case 'stdio':
         return 'Stdio';
     }
   };
 
-  const getTooltipContent = (server: McpServerConfig) => {
-    if (server.type === 'sse') {
+  const getTooltipContent = (
+    server: McpServerConfig,
+    state: McpServerState | undefined,
+  ) => {
+    if (
+      server.type === 'sse' ||
+      server.type === 'streamableHttp' ||
+      server.type === 'websocket'
+    ) {
       return <p>URL: {server.url}</p>;
     }
     if (server.type === 'stdio') {
       return (
-        <p>
-          Cmd: {server.command} {server.args.join(' ')}
-        </p>
+        <>
+          <p>
+            Cmd: {server.command} {server.args.join(' ')}
+          </p>
+          {state?.resolvedCommand && <p>Resolved: {state.resolvedCommand}</p>}
+        </>
       );
     }
     return null;
@@ -145,7 +209,7 @@ export const McpServerList: React.FC<McpServerListProps> = ({
             const status = getConnectionStatus(state);
             const isBuiltin = server.type === 'builtin-pseudo';
             const typeDisplay = getTypeDisplay(server);
-            const infoTooltipContent = getTooltipContent(server);
+            const infoTooltipContent = getTooltipContent(server, state);
 
             return (
               <TableRow
@@ -207,7 +271,11 @@ export const McpServerList: React.FC<McpServerListProps> = ({
                             onClick={(e) => {
                               e.preventDefault(); // Prevent row interactions if any
                               // Optionally copy details to clipboard
-                              if (server.type === 'sse')
+                              if (
+                                server.type === 'sse' ||
+                                server.type === 'streamableHttp' ||
+                                server.type === 'websocket'
+                              )
                                 navigator.clipboard.writeText(server.url);
                               if (server.type === 'stdio')
                                 navigator.clipboard.writeText(
@@ -253,6 +321,10 @@ export const McpServerList: React.FC<McpServerListProps> = ({
                         )}
                       </Button>
                     }
+                    {/* Process Logs Button */}
+                    {server.type === 'stdio' && state?.processId && (
+                      <ProcessLogsButton processId={state.processId} />
+                    )}
                     {/* Edit Button */}
                     {
                       // Don't allow editing built-in server details usually
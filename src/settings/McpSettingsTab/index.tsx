@@ -1,6 +1,7 @@
 import { Button } from '@/components/ui/Button';
 import { Dialog } from '@/components/ui/Dialog'; // Only Dialog needed here
 import { TooltipProvider } from '@/components/ui/Tooltip';
+import { isDesktopEnv } from '@/lib/env';
 import {
   addMcpServerAtom,
   connectAllMcpServersAtom,
@@ -8,9 +9,12 @@ import {
   deleteMcpServerAtom,
   disconnectAllMcpServersAtom,
   disconnectMcpServerAtom,
+  importMcpServersAtom,
   McpServerConfig,
   McpServerConfigSse, // Import specific types if needed
   McpServerConfigStdio, // Import specific types if needed
+  McpServerConfigStreamableHttp,
+  McpServerConfigWebSocket,
   mcpServersAtom,
   mcpServerStatesAtom,
   toggleMcpServerEnabledAtom,
@@ -19,7 +23,7 @@ import {
 import { useAtom, useSetAtom } from 'jotai';
 import React, { useCallback, useState } from 'react';
 // Adjust import path
-import { LuPlug, LuPlus, LuUnplug } from 'react-icons/lu';
+import { LuDownload, LuPlug, LuPlus, LuUnplug } from 'react-icons/lu';
 import { McpServerForm } from './McpServerForm'; // Adjust import path
 import { McpServerList } from './McpServerList'; // Adjust import path
 import type { McpServerFormData } from './schema'; // Adjust import path
@@ -36,6 +40,8 @@ export const McpSettingsTab: React.FC = () => {
   const deleteServer = useSetAtom(deleteMcpServerAtom);
   const addServer = useSetAtom(addMcpServerAtom);
   const updateServer = useSetAtom(updateMcpServerConfigAtom);
+  const importServers = useSetAtom(importMcpServersAtom);
+  const [isImporting, setIsImporting] = useState(false);
 
   // State for controlling the Add/Edit form dialog
   const [isFormOpen, setIsFormOpen] = useState(false);
@@ -63,6 +69,8 @@ export const McpSettingsTab: React.FC = () => {
       // and ensuring the correct structure based on 'type'
       let configPayload:
         | Omit<McpServerConfigSse, 'id' | 'enabled'>
+        | Omit<McpServerConfigStreamableHttp, 'id' | 'enabled'>
+        | Omit<McpServerConfigWebSocket, 'id' | 'enabled'>
         | Omit<McpServerConfigStdio, 'id' | 'enabled'>;
 
       if (data.type === 'sse') {
@@ -71,6 +79,25 @@ export const McpSettingsTab: React.FC = () => {
           description: data.description || '',
           type: 'sse',
           url: data.url, // Already validated as string
+          headers: data.headers,
+          autoApproveTools: data.autoApproveTools,
+        };
+      } else if (data.type === 'streamableHttp') {
+        configPayload = {
+          name: data.name,
+          description: data.description || '',
+          type: 'streamableHttp',
+          url: data.url,
+          headers: data.headers,
+          autoApproveTools: data.autoApproveTools,
+        };
+      } else if (data.type === 'websocket') {
+        configPayload = {
+          name: data.name,
+          description: data.description || '',
+          type: 'websocket',
+          url: data.url,
+          headers: data.headers,
           autoApproveTools: data.autoApproveTools,
         };
       } else if (data.type === 'stdio') {
@@ -80,6 +107,15 @@ export const McpSettingsTab: React.FC = () => {
           type: 'stdio',
           command: data.command, // Already validated as string
           args: data.args, // Already validated as string[]
+          env: data.env,
+          envPolicy: data.envPolicy,
+          cwd: data.cwd,
+          restart: data.restart,
+          framing: data.framing,
+          recordTraffic: data.recordTraffic,
+          limits: data.limits,
+          sandbox: data.sandbox,
+          runInShell: data.runInShell,
           autoApproveTools: data.autoApproveTools,
         };
       } else {
@@ -92,6 +128,8 @@ export const McpSettingsTab: React.FC = () => {
         // Update existing server - pass the full data including id
         updateServer({ id: data.id, ...configPayload } as
           | Omit<McpServerConfigSse, 'enabled'>
+          | Omit<McpServerConfigStreamableHttp, 'enabled'>
+          | Omit<McpServerConfigWebSocket, 'enabled'>
           | Omit<McpServerConfigStdio, 'enabled'>); // Cast needed as updateServer expects ID
       } else {
         // Add new server - pass payload without id (addServer atom should generate it)
@@ -131,6 +169,21 @@ export const McpSettingsTab: React.FC = () => {
             >
               <LuUnplug className="mr-2 h-4 w-4" /> Disconnect All
             </Button>
+            {isDesktopEnv() && (
+              <Button
+                variant="outline"
+                size="sm"
+                disabled={isImporting}
+                onClick={async () => {
+                  setIsImporting(true);
+                  await importServers();
+                  setIsImporting(false);
+                }}
+                title="Import servers from other MCP clients' config files"
+              >
+                <LuDownload className="mr-2 h-4 w-4" /> Import
+              </Button>
+            )}
             {/* Dialog Trigger using the shared state */}
             <Dialog open={isFormOpen} onOpenChange={setIsFormOpen}>
               <Button size="sm" onClick={() => handleOpenForm()}>
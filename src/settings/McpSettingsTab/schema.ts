@@ -14,12 +14,63 @@ const McpServerConfigBaseSchema = z.object({
 const McpServerConfigSseSchema = McpServerConfigBaseSchema.extend({
   type: z.literal('sse'),
   url: z.string().url('Invalid URL format.'),
+  headers: z.record(z.string()).optional().default({}),
+});
+
+const McpServerConfigStreamableHttpSchema = McpServerConfigBaseSchema.extend({
+  type: z.literal('streamableHttp'),
+  url: z
+    .string()
+    .url('Invalid URL format.')
+    .regex(/^https?:\/\//i, 'URL must start with http:// or https://.'),
+  headers: z.record(z.string()).optional().default({}),
+});
+
+const McpServerConfigWebSocketSchema = McpServerConfigBaseSchema.extend({
+  type: z.literal('websocket'),
+  url: z
+    .string()
+    .url('Invalid URL format.')
+    .regex(/^wss?:\/\//i, 'URL must start with ws:// or wss://.'),
+  headers: z.record(z.string()).optional().default({}),
 });
 
 const McpServerConfigStdioSchema = McpServerConfigBaseSchema.extend({
   type: z.literal('stdio'),
   command: z.string().min(1, 'Command is required.'),
   args: z.array(z.string()).optional().default([]),
+  env: z.record(z.string()).optional().default({}),
+  envPolicy: z.enum(['inherit', 'clear']).optional().default('inherit'),
+  cwd: z.string().optional(),
+  restart: z
+    .object({
+      mode: z.enum(['never', 'onFailure', 'always']),
+      maxRetries: z.number().int().min(0).optional(),
+      initialBackoffMs: z.number().int().min(0).optional(),
+      maxBackoffMs: z.number().int().min(0).optional(),
+    })
+    .optional(),
+  framing: z.enum(['ndjson', 'contentLength', 'auto']).optional(),
+  recordTraffic: z.boolean().optional(),
+  limits: z
+    .object({
+      maxMemoryMb: z.number().int().positive().optional(),
+      maxCpuSeconds: z.number().int().positive().optional(),
+      maxOpenFiles: z.number().int().positive().optional(),
+      maxProcesses: z.number().int().positive().optional(),
+      maxLifetimeMs: z.number().int().positive().optional(),
+    })
+    .optional(),
+  sandbox: z
+    .object({
+      writablePaths: z
+        .array(z.string().startsWith('/', 'Writable paths must be absolute.'))
+        .optional(),
+      denyNetwork: z.boolean().optional(),
+      privateTmp: z.boolean().optional(),
+    })
+    .optional(),
+  runInShell: z.boolean().optional(),
 });
 
 const McpServerConfigBuiltinSchema = McpServerConfigBaseSchema.extend({
@@ -37,6 +88,8 @@ const McpServerConfigMiniappSchema = McpServerConfigBaseSchema.extend({
 export const mcpServerFormSchema = z
   .discriminatedUnion('type', [
     McpServerConfigSseSchema,
+    McpServerConfigStreamableHttpSchema,
+    McpServerConfigWebSocketSchema,
     McpServerConfigStdioSchema,
     McpServerConfigBuiltinSchema,
     McpServerConfigMiniappSchema,
@@ -47,6 +100,14 @@ export const mcpServerFormSchema = z
     message: 'URL is required for SSE type',
     path: ['url'],
   })
+  .refine((data) => (data.type === 'streamableHttp' ? !!data.url : true), {
+    message: 'URL is required for Streamable HTTP type',
+    path: ['url'],
+  })
+  .refine((data) => (data.type === 'websocket' ? !!data.url : true), {
+    message: 'URL is required for WebSocket type',
+    path: ['url'],
+  })
   .refine((data) => (data.type === 'stdio' ? !!data.command : true), {
     message: 'Command is required for Stdio type',
     path: ['command'],
@@ -1,10 +1,24 @@
 // src/store/mcp.ts
+import { isDesktopEnv } from '@/lib/env';
+import {
+  importMcpServers,
+  MCP_IMPORT_CLIENT_LABELS,
+  McpImportReport,
+} from '@/lib/mcpImport';
 import {
   MiniappTransport,
   registerMiniappTransportForInstance,
   unregisterMiniappTransportForInstance,
 } from '@/lib/MiniappTransport';
-import { TauriStdioTransport } from '@/lib/TauriStdioTransport';
+import { TauriRemoteTransport } from '@/lib/TauriRemoteTransport';
+import {
+  describeProcessExit,
+  TauriStdioFraming,
+  TauriStdioResourceLimits,
+  TauriStdioRestartPolicy,
+  TauriStdioSandboxProfile,
+  TauriStdioTransport,
+} from '@/lib/TauriStdioTransport';
 import { atomWithSafeStorage } from '@/lib/utils';
 import { createBuiltinExprEvaluatorServer } from '@/mcp/builtinExprEvaluator';
 import { createBuiltinTimeServer } from '@/mcp/builtinTime'; // Import the time server creator
@@ -53,6 +67,21 @@ interface McpServerConfigBase {
 export interface McpServerConfigSse extends McpServerConfigBase {
   type: 'sse';
   url: string; // URL for the SSE endpoint
+  headers?: Record<string, string>; // Tauri only: sent with every request
+}
+
+// Configuration for Streamable HTTP type - Tauri only, connected by the backend
+export interface McpServerConfigStreamableHttp extends McpServerConfigBase {
+  type: 'streamableHttp';
+  url: string; // http:// or https:// MCP endpoint of the server
+  headers?: Record<string, string>; // Sent with every request, e.g. Authorization
+}
+
+// Configuration for WebSocket type - Tauri only, connected by the backend
+export interface McpServerConfigWebSocket extends McpServerConfigBase {
+  type: 'websocket';
+  url: string; // ws:// or wss:// URL of the server
+  headers?: Record<string, string>; // Sent with the handshake, e.g. Authorization
 }
 
 // Configuration for Stdio (Standard I/O) type - Tauri only
@@ -60,6 +89,15 @@ export interface McpServerConfigStdio extends McpServerConfigBase {
   type: 'stdio';
   command: string; // The command/executable to run
   args: string[]; // Arguments to pass to the command
+  env?: Record<string, string>; // Extra environment variables for the process
+  envPolicy?: 'inherit' | 'clear'; // Whether to start from Daan's environment
+  cwd?: string; // Working directory, defaults to Daan's own
+  restart?: TauriStdioRestartPolicy; // Restart by the backend when it exits
+  framing?: TauriStdioFraming; // Message delimiting on stdio, defaults to NDJSON
+  recordTraffic?: boolean; // Record the JSON-RPC traffic to a JSONL file
+  limits?: TauriStdioResourceLimits; // Memory, CPU, file and lifetime limits
+  sandbox?: TauriStdioSandboxProfile; // Confinement for untrusted servers (Linux)
+  runInShell?: boolean; // Hand the command to sh -c / cmd.exe /c as written
 }
 
 // Configuration for the built-in pseudo server (if applicable)
@@ -75,6 +113,8 @@ export interface McpServerConfigMiniapp extends McpServerConfigBase {
 
 export type McpServerConfig =
   | McpServerConfigSse
+  | McpServerConfigStreamableHttp
+  | McpServerConfigWebSocket
   | McpServerConfigStdio
   | McpServerConfigBuiltin
   | McpServerConfigMiniapp;
@@ -93,6 +133,8 @@ export interface McpServerState {
   client: Client | null;
   transport: Transport | null;
   capabilities: McpDiscoveredCapabilities | null;
+  processId?: string | null; // Backend process of the last stdio connection, for logs
+  resolvedCommand?: string | null; // Executable the stdio command resolved to
 }
 
 // --- MCP State Atoms ---
@@ -174,11 +216,31 @@ export const addMcpServerAtom = atom(
         | {
             type: 'sse';
             url: string;
+            headers?: Record<string, string>;
+          }
+        | {
+            type: 'streamableHttp';
+            url: string;
+            headers?: Record<string, string>;
+          }
+        | {
+            type: 'websocket';
+            url: string;
+            headers?: Record<string, string>;
           }
         | {
             type: 'stdio';
             command: string;
             args: string[];
+            env?: Record<string, string>;
+            envPolicy?: 'inherit' | 'clear';
+            cwd?: string;
+            restart?: TauriStdioRestartPolicy;
+            framing?: TauriStdioFraming;
+            recordTraffic?: boolean;
+            limits?: TauriStdioResourceLimits;
+            sandbox?: TauriStdioSandboxProfile;
+            runInShell?: boolean;
           }
       ),
   ) => {
@@ -200,6 +262,92 @@ export const addMcpServerAtom = atom(
   },
 );
 
+/**
+ * Action to add the servers configured in other MCP clients (and in the
+ * `.vscode/mcp.json` of the given workspace directories). They are added
+ * disabled; duplicates and entries Daan cannot run are left out.
+ */
+export const importMcpServersAtom = atom(
+  null,
+  async (get, set, paths: string[] = []): Promise<McpImportReport | null> => {
+    const servers = get(mcpServersAtom);
+    const existing = servers.flatMap((server) => {
+      switch (server.type) {
+        case 'stdio':
+          return [
+            {
+              name: server.name,
+              type: server.type,
+              command: server.command,
+              args: server.args,
+              env: server.env ?? {},
+              cwd: server.cwd,
+            },
+          ];
+        case 'sse':
+        case 'streamableHttp':
+        case 'websocket':
+          return [
+            {
+              name: server.name,
+              type: server.type,
+              url: server.url,
+              headers: server.headers ?? {},
+            },
+          ];
+        default:
+          return [];
+      }
+    });
+
+    let report: McpImportReport;
+    try {
+      report = await importMcpServers(paths, existing);
+    } catch (error) {
+      console.error('[MCP Import] Failed to import servers:', error);
+      toast.error(`Failed to import MCP servers: ${error}`);
+      return null;
+    }
+    report.unsupported.forEach((entry) =>
+      console.warn(
+        `[MCP Import] Skipped "${entry.name}" from ${entry.source}: ${entry.reason}`,
+      ),
+    );
+
+    // Names stay unique so servers remain distinguishable in the list
+    const names = new Set(servers.map((server) => server.name));
+    const imported: McpServerConfig[] = report.servers.map((server) => {
+      const label = MCP_IMPORT_CLIENT_LABELS[server.client];
+      const name = names.has(server.name)
+        ? `${server.name} (${label})`
+        : server.name;
+      names.add(name);
+      return {
+        ...server.config,
+        id: `custom::${uuidv4()}`,
+        name,
+        description: [`Imported from ${label}.`, ...server.notes].join(' '),
+        enabled: false,
+        autoApproveTools: false,
+      };
+    });
+    set(mcpServersAtom, (prev) => [...prev, ...imported]);
+
+    const skipped = report.duplicates.length + report.unsupported.length;
+    if (imported.length === 0 && skipped === 0) {
+      toast.info("No MCP servers found in other clients' config files.");
+    } else {
+      toast.success(
+        `Imported ${imported.length} MCP server(s)` +
+          (skipped > 0
+            ? `; skipped ${report.duplicates.length} duplicate(s) and ${report.unsupported.length} unsupported.`
+            : '.'),
+      );
+    }
+    return report;
+  },
+);
+
 /** Action to update an MCP server configuration. */
 export const updateMcpServerConfigAtom = atom(
   null,
@@ -244,7 +392,61 @@ export const updateMcpServerConfigAtom = atom(
             (update as Partial<McpServerConfigStdio>).args !==
               (server as McpServerConfigStdio).args;
 
-          if (typeChanged || urlChanged || commandOrArgsChanged) {
+          const stdioUpdate = update as Partial<McpServerConfigStdio>;
+          const stdioServer = server as McpServerConfigStdio;
+          const spawnOptionsChanged =
+            server.type === 'stdio' &&
+            ((stdioUpdate.env !== undefined &&
+              JSON.stringify(stdioUpdate.env) !==
+                JSON.stringify(stdioServer.env ?? {})) ||
+              (stdioUpdate.envPolicy !== undefined &&
+                stdioUpdate.envPolicy !==
+                  (stdioServer.envPolicy ?? 'inherit')) ||
+              (stdioUpdate.cwd !== undefined &&
+                stdioUpdate.cwd !== (stdioServer.cwd ?? '')) ||
+              (stdioUpdate.restart !== undefined &&
+                JSON.stringify(stdioUpdate.restart) !==
+                  JSON.stringify(stdioServer.restart)) ||
+              (stdioUpdate.framing !== undefined &&
+                stdioUpdate.framing !== (stdioServer.framing ?? 'ndjson')) ||
+              (stdioUpdate.recordTraffic !== undefined &&
+                stdioUpdate.recordTraffic !==
+                  (stdioServer.recordTraffic ?? false)) ||
+              (stdioUpdate.limits !== undefined &&
+                JSON.stringify(stdioUpdate.limits) !==
+                  JSON.stringify(stdioServer.limits)) ||
+              ('sandbox' in stdioUpdate &&
+                JSON.stringify(stdioUpdate.sandbox) !==
+                  JSON.stringify(stdioServer.sandbox)) ||
+              (stdioUpdate.runInShell !== undefined &&
+                stdioUpdate.runInShell !== (stdioServer.runInShell ?? false)));
+
+          const remoteUpdate = update as Partial<
+            | McpServerConfigSse
+            | McpServerConfigWebSocket
+            | McpServerConfigStreamableHttp
+          >;
+          const remoteServer = server as
+            | McpServerConfigSse
+            | McpServerConfigWebSocket
+            | McpServerConfigStreamableHttp;
+          const remoteChanged =
+            (server.type === 'sse' ||
+              server.type === 'websocket' ||
+              server.type === 'streamableHttp') &&
+            ((remoteUpdate.url !== undefined &&
+              remoteUpdate.url !== remoteServer.url) ||
+              (remoteUpdate.headers !== undefined &&
+                JSON.stringify(remoteUpdate.headers) !==
+                  JSON.stringify(remoteServer.headers ?? {})));
+
+          if (
+            typeChanged ||
+            urlChanged ||
+            remoteChanged ||
+            commandOrArgsChanged ||
+            spawnOptionsChanged
+          ) {
             needsReconnect = true;
           }
 
@@ -411,14 +613,71 @@ export const connectMcpServerAtom = atom(
           `[MCP Connect] Using PseudoTransport with built-in server for ${config.id}`,
         );
         // --- End Refactor ---
+      } else if (config.type === 'sse' && config.url && isDesktopEnv()) {
+        // The backend connection is not subject to the webview's CORS rules
+        console.log(
+          `[MCP Connect] Using TauriRemoteTransport for ${config.id}`,
+        );
+        transport = new TauriRemoteTransport('sse', config.url, {
+          headers: config.headers,
+        });
       } else if (config.type === 'sse' && config.url) {
         console.log(`[MCP Connect] Using SSEClientTransport for ${config.id}`);
         transport = new SSEClientTransport(new URL(config.url));
+      } else if (config.type === 'streamableHttp' && config.url) {
+        console.log(
+          `[MCP Connect] Using TauriRemoteTransport (Streamable HTTP) for ${config.id}`,
+        );
+        transport = new TauriRemoteTransport('streamableHttp', config.url, {
+          headers: config.headers,
+        });
+      } else if (config.type === 'websocket' && config.url) {
+        console.log(
+          `[MCP Connect] Using TauriRemoteTransport (WebSocket) for ${config.id}`,
+        );
+        transport = new TauriRemoteTransport('websocket', config.url, {
+          headers: config.headers,
+        });
       } else if (config.type === 'stdio' && config.command && config.args) {
         console.log(
           `[MCP Connect] Using StdioClientTransport for ${config.id}`,
         );
-        transport = new TauriStdioTransport(config.command, config.args);
+        const stdioTransport = new TauriStdioTransport(
+          config.command,
+          config.args,
+          {
+            env: config.env,
+            envPolicy: config.envPolicy,
+            cwd: config.cwd,
+            restart: config.restart,
+            framing: config.framing,
+            recordTraffic: config.recordTraffic,
+            limits: config.limits,
+            sandbox: config.sandbox,
+            runInShell: config.runInShell,
+          },
+        );
+        // A server that dies while connected shows up as an error with its reason
+        stdioTransport.onexit = (info) => {
+          if (info.stopRequested) return;
+          const reason = describeProcessExit(info);
+          const current = get(mcpServerStatesAtom).get(serverId);
+          if (!current?.isConnected || current.transport !== stdioTransport) {
+            return;
+          }
+          set(mcpServerStatesAtom, (prevMap) =>
+            new Map(prevMap).set(serverId, {
+              ...current,
+              isConnected: false,
+              client: null,
+              transport: null,
+              capabilities: null,
+              error: reason,
+            }),
+          );
+          toast.error(`MCP Server "${config.name}" stopped: ${reason}`);
+        };
+        transport = stdioTransport;
       } else if (config.type === 'miniapp') {
         console.log(
           `[MCP Connect] Using MiniappTransport for ${config.id}, target: ${config.targetMiniappId}`,
@@ -550,6 +809,14 @@ export const connectMcpServerAtom = atom(
         transport: transport,
         capabilities: capabilities,
         error: null,
+        processId:
+          transport instanceof TauriStdioTransport
+            ? transport.backendProcessId
+            : null,
+        resolvedCommand:
+          transport instanceof TauriStdioTransport
+            ? transport.resolvedPath
+            : null,
       };
       set(mcpServerStatesAtom, (prevMap) =>
         new Map(prevMap).set(serverId, connectedState),
@@ -596,7 +863,15 @@ export const connectMcpServerAtom = atom(
         client: null, // Clear client/transport on error
         transport: null,
         capabilities: null,
-        error: error.message || 'Unknown connection error',
+        // A server that exited during the handshake explains itself better
+        error:
+          transport instanceof TauriStdioTransport && transport.lastExit
+            ? describeProcessExit(transport.lastExit)
+            : error.message || 'Unknown connection error',
+        processId:
+          transport instanceof TauriStdioTransport
+            ? transport.backendProcessId
+            : null,
       };
       set(mcpServerStatesAtom, (prevMap) =>
         new Map(prevMap).set(serverId, errorState),
@@ -680,6 +955,7 @@ export const disconnectMcpServerAtom = atom(
         client: null,
         transport: null,
         capabilities: null, // Clear capabilities on disconnect
+        processId: currentState?.processId, // Keep logs reachable after disconnect
       };
       set(mcpServerStatesAtom, (prevMap) =>
         new Map(prevMap).set(serverId, disconnectedState),